@@ -1,56 +1,24 @@
-use egui_wgpu::{EguiRenderer, EguiRendererDescriptor, EventBridge, UiState};
+use egui_wgpu::{EguiRenderer, EguiRendererDescriptor, UiState};
 use wgpu::TextureFormat;
 use winit::{
-    dpi::PhysicalSize, //    window::Window,
-    event::{ElementState, Event, WindowEvent},
+    event::{Event, WindowEvent},
     event_loop::{ControlFlow, EventLoop},
 };
 
 const FMT: TextureFormat = TextureFormat::Bgra8UnormSrgb;
-struct EventWrapper<'a, 'b>(&'b Event<'a, ()>);
-
-impl<'a, 'b> Into<EventBridge> for EventWrapper<'a, 'b> {
-    fn into(self) -> EventBridge {
-        match self.0 {
-            Event::WindowEvent { event, .. } => match event {
-                WindowEvent::Resized(PhysicalSize { width, height }) => EventBridge::Resize {
-                    w: *width as f32,
-                    h: *height as f32,
-                },
-                WindowEvent::CursorMoved { position: p, .. } => EventBridge::MouseMove {
-                    x: p.x as f32,
-                    y: p.y as f32,
-                },
-                WindowEvent::MouseWheel {
-                    delta: winit::event::MouseScrollDelta::LineDelta(x, y),
-                    ..
-                } => EventBridge::Scroll { x: *x, y: *y },
-                WindowEvent::MouseWheel {
-                    delta: winit::event::MouseScrollDelta::PixelDelta(pos),
-                    ..
-                } => EventBridge::Scroll {
-                    x: pos.x as f32,
-                    y: pos.y as f32,
-                },
-                WindowEvent::MouseInput { state, .. } => match state {
-                    ElementState::Pressed => EventBridge::MouseDown,
-                    ElementState::Released => EventBridge::MouseUp,
-                },
-                WindowEvent::ScaleFactorChanged { scale_factor, .. } => {
-                    EventBridge::PppChanged(*scale_factor as f32)
-                }
-                _ => EventBridge::Ignore,
-            },
-            _ => EventBridge::Ignore,
-        }
-    }
-}
 
 #[derive(Copy, Clone)]
 struct UI;
 
+enum Action {
+    Quit,
+}
+
 impl UiState for UI {
-    fn draw(&self, ui: &mut egui::Ui) {
+    type Action = Action;
+
+    fn draw(&mut self, ui: &mut egui::Ui) -> Option<Action> {
+        let mut action = None;
         egui::Window::new("Debug")
             .default_size(egui::vec2(200.0, 100.0))
             .show(ui.ctx(), |ui| {
@@ -58,9 +26,10 @@ impl UiState for UI {
                 ui.separator();
                 ui.label("Oh Yes!");
                 if ui.button("Quit").clicked {
-                    std::process::exit(0);
+                    action = Some(Action::Quit);
                 }
             });
+        action
     }
 }
 
@@ -99,6 +68,10 @@ fn main() {
             state: ui_state,
             screen_size: window.inner_size().into(),
             ppp: window.scale_factor() as f32,
+            load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+            color_space: None,
+            hdr: None,
+            sample_count: 1,
         },
     );
 
@@ -113,7 +86,7 @@ fn main() {
     let mut swap_chain = device.create_swap_chain(&surface, &sc_desc);
 
     event_loop.run(move |event, _, control_flow| {
-        egui_renderer.consume_event(EventWrapper(&event));
+        egui_renderer.consume_winit_event(&event);
         match event {
             Event::WindowEvent {
                 event: WindowEvent::Resized(size),
@@ -123,13 +96,25 @@ fn main() {
                 sc_desc.height = size.height;
                 swap_chain = device.create_swap_chain(&surface, &sc_desc);
             }
-            // This is lazy and bad do not cargo cult it.
-            Event::MainEventsCleared => {
+            Event::MainEventsCleared if egui_renderer.needs_repaint() => {
+                window.request_redraw();
+            }
+            Event::RedrawRequested(_) => {
                 let frame = swap_chain.get_current_frame().expect("Swap Chain Failed");
                 let encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
                     label: Some("egui-wgpu :: ui encoder"),
                 });
-                egui_renderer.draw_on(encoder, &device, &queue, frame);
+                let output = egui_renderer.draw_on(encoder, &device, &queue, frame);
+                if let Some(Action::Quit) = output.action {
+                    *control_flow = ControlFlow::Exit;
+                }
+            }
+            // only wake up again when the UI has something to show
+            Event::RedrawEventsCleared if *control_flow != ControlFlow::Exit => {
+                *control_flow = match egui_renderer.next_repaint() {
+                    Some(at) => ControlFlow::WaitUntil(at),
+                    None => ControlFlow::Wait,
+                };
             }
             Event::WindowEvent {
                 event: WindowEvent::CloseRequested,
@@ -0,0 +1,100 @@
+use wgpu::*;
+
+/// A gpu buffer that is reused across frames and grown by doubling
+/// whenever a frame's data does not fit.
+pub struct GrowableBuffer {
+    pub buf: Buffer,
+    capacity: BufferAddress,
+    usage: BufferUsage,
+    label: &'static str,
+}
+
+impl GrowableBuffer {
+    pub fn new(dev: &Device, label: &'static str, usage: BufferUsage, capacity: u64) -> Self {
+        let usage = usage | BufferUsage::COPY_DST;
+        Self {
+            buf: create(dev, label, usage, capacity),
+            capacity,
+            usage,
+            label,
+        }
+    }
+
+    /// Uploads `data` to the start of the buffer, reallocating first if it
+    /// does not fit. Returns true if a reallocation happened.
+    pub fn write(&mut self, dev: &Device, queue: &Queue, data: &[u8]) -> bool {
+        let grown = reserve(&mut self.capacity, data.len() as BufferAddress);
+        if grown {
+            self.buf = create(dev, self.label, self.usage, self.capacity);
+        }
+        if !data.is_empty() {
+            queue.write_buffer(&self.buf, 0, data);
+        }
+        grown
+    }
+}
+
+fn create(dev: &Device, label: &str, usage: BufferUsage, size: u64) -> Buffer {
+    dev.create_buffer(&BufferDescriptor {
+        label: Some(label),
+        size,
+        usage,
+        mapped_at_creation: false,
+    })
+}
+
+/// Grows `capacity` to fit `required` bytes, returns true if it had to.
+fn reserve(capacity: &mut u64, required: u64) -> bool {
+    if required <= *capacity {
+        return false;
+    }
+    *capacity = grown_capacity(*capacity, required);
+    true
+}
+
+/// Doubles `current` until it can hold `required` bytes, keeping the result
+/// a multiple of `COPY_BUFFER_ALIGNMENT`.
+fn grown_capacity(current: u64, required: u64) -> u64 {
+    let mut capacity = current.max(COPY_BUFFER_ALIGNMENT);
+    while capacity < required {
+        capacity *= 2;
+    }
+    capacity.div_ceil(COPY_BUFFER_ALIGNMENT) * COPY_BUFFER_ALIGNMENT
+}
+
+#[cfg(test)]
+mod test {
+    use super::{grown_capacity, reserve};
+
+    #[test]
+    fn capacity_doubles_until_it_fits() {
+        assert_eq!(grown_capacity(1024, 1025), 2048);
+        assert_eq!(grown_capacity(1024, 5000), 8192);
+    }
+
+    #[test]
+    fn capacity_is_never_zero() {
+        assert_eq!(grown_capacity(0, 3), 4);
+    }
+
+    #[test]
+    fn steady_frames_do_not_reallocate() {
+        let mut capacity = 1024;
+        let frames = [3000, 2800, 3000, 2990, 0, 3000];
+        let reallocations = frames
+            .iter()
+            .filter(|&&size| reserve(&mut capacity, size))
+            .count();
+        assert_eq!(reallocations, 1);
+        assert_eq!(capacity, 4096);
+    }
+
+    #[test]
+    fn larger_frames_reallocate_again() {
+        let mut capacity = 1024;
+        assert!(reserve(&mut capacity, 3000));
+        assert!(!reserve(&mut capacity, 4096));
+        assert!(reserve(&mut capacity, 4097));
+        assert_eq!(capacity, 8192);
+    }
+}
@@ -0,0 +1,223 @@
+use crate::{EguiRenderer, EventBridge, FrameOutput, UiState};
+use futures::executor::block_on;
+use wgpu::*;
+
+/// Tightly packed 8 bit RGBA pixels, rows top to bottom.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RgbaImage {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+
+impl RgbaImage {
+    /// Encodes the image as a PNG into `w`.
+    #[cfg(feature = "png")]
+    pub fn write_png<W: std::io::Write>(&self, w: W) -> Result<(), png::EncodingError> {
+        let mut encoder = png::Encoder::new(w, self.width, self.height);
+        encoder.set_color(png::ColorType::RGBA);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.write_header()?.write_image_data(&self.pixels)
+    }
+
+    /// Decodes an 8 bit RGBA PNG, as written by [`RgbaImage::write_png`].
+    #[cfg(feature = "png")]
+    pub fn read_png<R: std::io::Read>(r: R) -> Result<Self, png::DecodingError> {
+        let (info, mut reader) = png::Decoder::new(r).read_info()?;
+        if info.color_type != png::ColorType::RGBA || info.bit_depth != png::BitDepth::Eight {
+            return Err(png::DecodingError::Other(
+                "egui-wgpu :: only 8 bit RGBA images are supported".into(),
+            ));
+        }
+        let mut pixels = vec![0; info.buffer_size()];
+        reader.next_frame(&mut pixels)?;
+        Ok(Self {
+            width: info.width,
+            height: info.height,
+            pixels,
+        })
+    }
+}
+
+impl<S> EguiRenderer<S>
+where
+    S: UiState,
+{
+    /// Renders the next UI frame at `size` physical pixels into an
+    /// offscreen texture and reads it back, along with the frame's output.
+    /// The UI goes back to its previous size afterwards. Only renderers
+    /// for 8 bit RGBA or BGRA formats can be captured.
+    pub fn capture(
+        &mut self,
+        dev: &Device,
+        queue: &Queue,
+        size: (u32, u32),
+    ) -> (RgbaImage, FrameOutput<S::Action>) {
+        assert_capturable(self.fmt);
+        let (width, height) = size;
+        let (w, h) = self.ui.physical_size;
+        self.consume_event(EventBridge::Resize {
+            w: width as f32,
+            h: height as f32,
+        });
+
+        let extent = Extent3d {
+            width,
+            height,
+            depth: 1,
+        };
+        let target = dev.create_texture(&TextureDescriptor {
+            label: Some("egui-wgpu :: capture_texture"),
+            size: extent,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format: self.fmt,
+            usage: TextureUsage::OUTPUT_ATTACHMENT | TextureUsage::COPY_SRC,
+        });
+
+        let padded_row = padded_bytes_per_row(width);
+        let readback = dev.create_buffer(&BufferDescriptor {
+            label: Some("egui-wgpu :: capture_buffer"),
+            size: padded_row as u64 * height as u64,
+            usage: BufferUsage::MAP_READ | BufferUsage::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let com = dev.create_command_encoder(&CommandEncoderDescriptor {
+            label: Some("egui-wgpu :: capture_encoder"),
+        });
+        let view = target.create_view(&Default::default());
+        let output = self.draw_to_view(com, dev, queue, &view, size);
+
+        let mut com = dev.create_command_encoder(&CommandEncoderDescriptor {
+            label: Some("egui-wgpu :: capture_copy_encoder"),
+        });
+        com.copy_texture_to_buffer(
+            TextureCopyView {
+                texture: &target,
+                mip_level: 0,
+                origin: Origin3d::ZERO,
+            },
+            BufferCopyView {
+                buffer: &readback,
+                layout: TextureDataLayout {
+                    offset: 0,
+                    bytes_per_row: padded_row,
+                    rows_per_image: height,
+                },
+            },
+            extent,
+        );
+        queue.submit(Some(com.finish()));
+
+        let slice = readback.slice(..);
+        let mapped = slice.map_async(MapMode::Read);
+        dev.poll(Maintain::Wait);
+        block_on(mapped).expect("egui-wgpu :: failed to map capture buffer");
+
+        let mut pixels = unpad_rows(&slice.get_mapped_range(), width, height);
+        readback.unmap();
+
+        if is_bgra(self.fmt) {
+            bgra_to_rgba(&mut pixels);
+        }
+
+        // later frames go to the window again, scissors and the
+        // multisampled texture must match it
+        self.consume_event(EventBridge::Resize { w, h });
+
+        let image = RgbaImage {
+            width,
+            height,
+            pixels,
+        };
+        (image, output)
+    }
+}
+
+/// Readback assumes 4 bytes per texel that map straight to [`RgbaImage`].
+fn assert_capturable(fmt: TextureFormat) {
+    assert!(
+        is_bgra(fmt)
+            || matches!(
+                fmt,
+                TextureFormat::Rgba8Unorm | TextureFormat::Rgba8UnormSrgb
+            ),
+        "egui-wgpu :: capture needs an 8 bit RGBA or BGRA format, not {:?}",
+        fmt
+    );
+}
+
+/// Rows copied out of a texture must start on a
+/// `COPY_BYTES_PER_ROW_ALIGNMENT` boundary.
+fn padded_bytes_per_row(width: u32) -> u32 {
+    let unpadded = width * 4;
+    let align = COPY_BYTES_PER_ROW_ALIGNMENT;
+    unpadded.div_ceil(align) * align
+}
+
+fn unpad_rows(padded: &[u8], width: u32, height: u32) -> Vec<u8> {
+    let row = width as usize * 4;
+    padded
+        .chunks(padded_bytes_per_row(width) as usize)
+        .take(height as usize)
+        .flat_map(|r| &r[..row])
+        .copied()
+        .collect()
+}
+
+fn is_bgra(fmt: TextureFormat) -> bool {
+    matches!(
+        fmt,
+        TextureFormat::Bgra8Unorm | TextureFormat::Bgra8UnormSrgb
+    )
+}
+
+fn bgra_to_rgba(pixels: &mut [u8]) {
+    pixels.chunks_mut(4).for_each(|px| px.swap(0, 2));
+}
+
+#[cfg(test)]
+mod test {
+    use super::{assert_capturable, bgra_to_rgba, padded_bytes_per_row, unpad_rows};
+    use wgpu::TextureFormat;
+
+    #[test]
+    fn rows_are_padded_to_256_bytes() {
+        assert_eq!(padded_bytes_per_row(1), 256);
+        assert_eq!(padded_bytes_per_row(64), 256);
+        assert_eq!(padded_bytes_per_row(65), 512);
+    }
+
+    #[test]
+    fn padding_is_stripped_from_each_row() {
+        let (w, h) = (3, 2);
+        let mut padded = vec![0u8; 256 * h];
+        padded[..12].copy_from_slice(&[1; 12]);
+        padded[256..268].copy_from_slice(&[2; 12]);
+        let pixels = unpad_rows(&padded, w, h as u32);
+        assert_eq!(pixels.len(), 24);
+        assert_eq!(&pixels[..12], &[1; 12]);
+        assert_eq!(&pixels[12..], &[2; 12]);
+    }
+
+    #[test]
+    fn eight_bit_formats_can_be_captured() {
+        assert_capturable(TextureFormat::Rgba8UnormSrgb);
+        assert_capturable(TextureFormat::Bgra8Unorm);
+    }
+
+    #[test]
+    #[should_panic(expected = "8 bit RGBA or BGRA")]
+    fn float_formats_cannot_be_captured() {
+        assert_capturable(TextureFormat::Rgba16Float);
+    }
+
+    #[test]
+    fn bgra_is_swizzled() {
+        let mut px = vec![1, 2, 3, 4];
+        bgra_to_rgba(&mut px);
+        assert_eq!(px, vec![3, 2, 1, 4]);
+    }
+}
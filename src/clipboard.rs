@@ -0,0 +1,23 @@
+/// Access to the host's clipboard. Pasting reads from it, text egui
+/// copies or cuts is written to it.
+pub trait Clipboard {
+    fn get(&mut self) -> Option<String>;
+    fn set(&mut self, text: String);
+}
+
+/// A clipboard that lives only as long as the renderer, used until the
+/// host provides its own and handy in tests.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MemoryClipboard {
+    pub contents: Option<String>,
+}
+
+impl Clipboard for MemoryClipboard {
+    fn get(&mut self) -> Option<String> {
+        self.contents.clone()
+    }
+
+    fn set(&mut self, text: String) {
+        self.contents = Some(text);
+    }
+}
@@ -0,0 +1,75 @@
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc,
+};
+use std::time::Instant;
+
+/// Where the renderer gets egui's time from, read once per frame.
+pub trait Clock {
+    /// Seconds since some fixed point in the past.
+    fn now(&mut self) -> f64;
+}
+
+/// Wall clock time since the clock was created.
+#[derive(Debug, Clone, Copy)]
+pub struct SystemClock {
+    start: Instant,
+}
+
+impl Default for SystemClock {
+    fn default() -> Self {
+        Self {
+            start: Instant::now(),
+        }
+    }
+}
+
+impl Clock for SystemClock {
+    fn now(&mut self) -> f64 {
+        self.start.elapsed().as_nanos() as f64 * 1e-9
+    }
+}
+
+/// A clock that only moves when told to, for tests and replays. Clones
+/// share the same time, so keep one to step the clock the renderer owns.
+#[derive(Debug, Clone, Default)]
+pub struct ManualClock {
+    bits: Arc<AtomicU64>,
+}
+
+impl ManualClock {
+    pub fn set(&self, seconds: f64) {
+        self.bits.store(seconds.to_bits(), Ordering::Relaxed);
+    }
+
+    pub fn advance(&self, seconds: f64) {
+        self.set(self.get() + seconds);
+    }
+
+    pub fn get(&self) -> f64 {
+        f64::from_bits(self.bits.load(Ordering::Relaxed))
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&mut self) -> f64 {
+        self.get()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Clock, ManualClock};
+
+    #[test]
+    fn manual_clock_is_shared_between_clones() {
+        let handle = ManualClock::default();
+        let mut owned = handle.clone();
+        assert_eq!(owned.now(), 0.);
+        handle.advance(0.5);
+        handle.advance(0.25);
+        assert_eq!(owned.now(), 0.75);
+        handle.set(2.);
+        assert_eq!(owned.now(), 2.);
+    }
+}
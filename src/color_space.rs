@@ -0,0 +1,136 @@
+use wgpu::TextureFormat;
+
+/// The space the fragment shader writes colors in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputColorSpace {
+    /// Linear values, for `*Srgb` targets that encode on write and for
+    /// float targets that are tone mapped later.
+    Linear,
+    /// sRGB encoded values blended as is, for plain `*Unorm` targets.
+    Gamma,
+}
+
+impl OutputColorSpace {
+    /// Picks the space matching what `fmt` does with written values.
+    pub fn detect(fmt: TextureFormat) -> Self {
+        use TextureFormat::*;
+        match fmt {
+            Rgba8UnormSrgb | Bgra8UnormSrgb => OutputColorSpace::Linear,
+            R16Float | Rg16Float | Rgba16Float | R32Float | Rg32Float | Rgba32Float
+            | Rg11b10Float => OutputColorSpace::Linear,
+            _ => OutputColorSpace::Gamma,
+        }
+    }
+}
+
+/// Brightness of 1.0 in an scRGB buffer.
+const SCRGB_WHITE_NITS: f32 = 80.;
+
+/// How the UI is placed on an HDR target, only allowed with
+/// [`OutputColorSpace::Linear`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HdrSettings {
+    /// Brightness UI white is shown at, assuming 1.0 in the target is
+    /// 80 nits as in scRGB.
+    pub paper_white_nits: f32,
+    /// Makes translucent parts of the UI cover more of what is below, so
+    /// bright scene content does not shine through panels that look
+    /// fine over SDR content. Has no effect at 80 nits.
+    pub tone_aware_blending: bool,
+}
+
+impl Default for HdrSettings {
+    /// Reference white from ITU-R BT.2408.
+    fn default() -> Self {
+        Self {
+            paper_white_nits: 203.,
+            tone_aware_blending: false,
+        }
+    }
+}
+
+impl HdrSettings {
+    /// Factor the UI color is multiplied with.
+    pub(crate) fn brightness(&self) -> f32 {
+        self.paper_white_nits / SCRGB_WHITE_NITS
+    }
+
+    /// Exponent applied to the transparency of every fragment.
+    pub(crate) fn alpha_power(&self) -> f32 {
+        if self.tone_aware_blending {
+            self.brightness().max(1.)
+        } else {
+            1.
+        }
+    }
+}
+
+/// Panics for HDR settings on a gamma target, its shader has no
+/// brightness or blending to apply them to.
+pub(crate) fn assert_hdr_applies(space: OutputColorSpace, hdr: Option<HdrSettings>) {
+    assert!(
+        hdr.is_none() || space == OutputColorSpace::Linear,
+        "egui-wgpu :: HdrSettings need a linear color space, use a float \
+         or sRGB format or set color_space to Linear"
+    );
+}
+
+#[cfg(test)]
+mod test {
+    use super::OutputColorSpace::{self, *};
+    use super::{assert_hdr_applies, HdrSettings};
+    use wgpu::TextureFormat;
+
+    #[test]
+    fn srgb_and_float_targets_are_linear() {
+        assert_eq!(
+            OutputColorSpace::detect(TextureFormat::Bgra8UnormSrgb),
+            Linear
+        );
+        assert_eq!(OutputColorSpace::detect(TextureFormat::Rgba16Float), Linear);
+    }
+
+    #[test]
+    fn unorm_targets_are_gamma() {
+        assert_eq!(OutputColorSpace::detect(TextureFormat::Rgba8Unorm), Gamma);
+        assert_eq!(OutputColorSpace::detect(TextureFormat::Bgra8Unorm), Gamma);
+        assert_eq!(OutputColorSpace::detect(TextureFormat::Rgb10a2Unorm), Gamma);
+    }
+
+    #[test]
+    fn paper_white_is_relative_to_scrgb_white() {
+        let hdr = HdrSettings {
+            paper_white_nits: 240.,
+            ..Default::default()
+        };
+        assert_eq!(hdr.brightness(), 3.);
+        assert_eq!(hdr.alpha_power(), 1.);
+    }
+
+    #[test]
+    fn tone_aware_blending_never_lets_more_through() {
+        let dim = HdrSettings {
+            paper_white_nits: 40.,
+            tone_aware_blending: true,
+        };
+        assert_eq!(dim.alpha_power(), 1.);
+
+        let bright = HdrSettings {
+            paper_white_nits: 160.,
+            tone_aware_blending: true,
+        };
+        assert_eq!(bright.alpha_power(), 2.);
+    }
+
+    #[test]
+    fn hdr_applies_to_linear_targets() {
+        assert_hdr_applies(Linear, Some(HdrSettings::default()));
+        assert_hdr_applies(Gamma, None);
+    }
+
+    #[test]
+    #[should_panic(expected = "linear color space")]
+    fn hdr_is_rejected_on_gamma_targets() {
+        assert_hdr_applies(Gamma, Some(HdrSettings::default()));
+    }
+}
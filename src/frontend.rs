@@ -0,0 +1,465 @@
+//! Everything about running the UI that does not need a GPU: input,
+//! time and the egui context.
+
+use crate::{
+    input,
+    scroll::PendingScroll,
+    touch::{Synthesized, Touches},
+    ButtonEvent, Clipboard, Clock, EventBridge, FrameOutput, MemoryClipboard, Modifiers,
+    MouseButton, ScrollSettings, SystemClock, UiState,
+};
+use egui::{paint::PaintJobs, pos2, vec2, Context, RawInput};
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+/// Shortest time between two frames while the UI keeps changing.
+const FRAME_INTERVAL: Duration = Duration::from_micros(1_000_000 / 60);
+
+pub(crate) struct Frontend<S: UiState> {
+    pub ctx: Arc<Context>,
+    pub state: S,
+    raw_input: RawInput,
+    pub physical_size: (f32, f32),
+    modifiers: Modifiers,
+    touches: Touches,
+    scroll: PendingScroll,
+    pub scroll_settings: ScrollSettings,
+    pub clipboard: Box<dyn Clipboard>,
+    pub clock: Box<dyn Clock>,
+    zoom_delta: f32,
+    buttons: Vec<ButtonEvent>,
+    /// Non-primary buttons the state was told are down.
+    held: Vec<MouseButton>,
+    input_since_frame: bool,
+    egui_wants_repaint: bool,
+    last_frame: Option<Instant>,
+}
+
+impl<S: UiState> Frontend<S> {
+    /// `screen_size` is in physical pixels.
+    pub fn new(state: S, screen_size: (f32, f32), ppp: f32) -> Self {
+        let mut ctx = Context::new();
+        let raw_input = RawInput {
+            pixels_per_point: Some(ppp),
+            screen_size: vec2(screen_size.0 / ppp, screen_size.1 / ppp),
+            ..Default::default()
+        };
+        // builds the font atlas before the first frame
+        let _ = ctx.begin_frame(raw_input.clone());
+
+        Self {
+            ctx,
+            state,
+            raw_input,
+            physical_size: screen_size,
+            modifiers: Modifiers::default(),
+            touches: Touches::default(),
+            scroll: PendingScroll::default(),
+            scroll_settings: ScrollSettings::default(),
+            clipboard: Box::new(MemoryClipboard::default()),
+            clock: Box::new(SystemClock::default()),
+            zoom_delta: 1.,
+            buttons: Vec::new(),
+            held: Vec::new(),
+            input_since_frame: true,
+            egui_wants_repaint: false,
+            last_frame: None,
+        }
+    }
+
+    /// Hands out the state for a change the next frame has to show.
+    pub fn state_mut(&mut self) -> &mut S {
+        self.request_repaint();
+        &mut self.state
+    }
+
+    /// Makes [`Frontend::needs_repaint`] true for changes that do not
+    /// come in as input.
+    pub fn request_repaint(&mut self) {
+        self.input_since_frame = true;
+    }
+
+    pub fn ppp(&self) -> f32 {
+        self.raw_input.pixels_per_point.unwrap_or(1.)
+    }
+
+    /// The screen size in points.
+    pub fn screen_size(&self) -> (f32, f32) {
+        let egui::Vec2 { x, y } = self.raw_input.screen_size;
+        (x, y)
+    }
+
+    pub fn consume(&mut self, input: EventBridge) {
+        let ppp = self.ppp();
+        if input != EventBridge::Ignore {
+            self.input_since_frame = true;
+        }
+
+        match input {
+            EventBridge::MouseDown { button, pos } => self.mouse_button(button, pos, true),
+            EventBridge::MouseUp { button, pos } => self.mouse_button(button, pos, false),
+            EventBridge::Scroll { x, y } => self.scroll.push(vec2(x, y)),
+            EventBridge::ScrollLines { x, y } => {
+                let points_per_line = self.scroll_settings.points_per_line;
+                self.scroll.push(vec2(x, y) * points_per_line);
+            }
+            EventBridge::MouseMove { x, y } => {
+                self.raw_input.mouse_pos = Some(pos2(x / ppp, y / ppp))
+            }
+            EventBridge::Resize { w, h } => {
+                self.physical_size = (w, h);
+                self.update_screen_size();
+            }
+            EventBridge::PppChanged(dpi) => self.set_dpi(dpi),
+            EventBridge::Key { key, pressed } => {
+                if input::is_paste(key, pressed, self.modifiers) {
+                    // egui 0.1 takes pasted text as typed text
+                    let text = self.clipboard.get();
+                    self.raw_input.events.extend(text.map(egui::Event::Text));
+                } else {
+                    let events = input::key_events(key, pressed, self.modifiers);
+                    self.raw_input.events.extend(events);
+                }
+            }
+            EventBridge::Modifiers(modifiers) => self.modifiers = modifiers,
+            EventBridge::Char(c) => {
+                let text = input::text_event(c, self.modifiers);
+                self.raw_input.events.extend(text);
+            }
+            EventBridge::PointerLeft => self.raw_input.mouse_pos = None,
+            EventBridge::Focus(false) => {
+                // releases arriving while unfocused are never seen
+                self.raw_input.mouse_down = false;
+                for button in std::mem::take(&mut self.held) {
+                    self.buttons.push(ButtonEvent {
+                        button,
+                        pressed: false,
+                        pos: self.raw_input.mouse_pos,
+                    });
+                }
+                self.modifiers = Modifiers::default();
+                self.touches = Touches::default();
+            }
+            EventBridge::Touch { id, phase, x, y } => {
+                let pos = pos2(x / ppp, y / ppp);
+                for synth in self.touches.update(id, phase, pos) {
+                    self.apply_touch(synth);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn apply_touch(&mut self, synth: Synthesized) {
+        match synth {
+            Synthesized::Move(pos) => self.raw_input.mouse_pos = Some(pos),
+            Synthesized::Down => self.raw_input.mouse_down = true,
+            Synthesized::Up => self.raw_input.mouse_down = false,
+            Synthesized::Leave => self.raw_input.mouse_pos = None,
+            Synthesized::Scroll(delta) => self.scroll.push(delta),
+            Synthesized::Zoom(factor) => self.zoom_delta *= factor,
+        }
+    }
+
+    /// egui only knows about the primary button, the others are queued
+    /// for [`UiState::buttons`].
+    fn mouse_button(&mut self, button: MouseButton, pos: Option<(f32, f32)>, pressed: bool) {
+        let ppp = self.ppp();
+        if let Some((x, y)) = pos {
+            self.raw_input.mouse_pos = Some(pos2(x / ppp, y / ppp));
+        }
+        if button == MouseButton::Primary {
+            self.raw_input.mouse_down = pressed;
+        } else {
+            self.held.retain(|held| *held != button);
+            if pressed {
+                self.held.push(button);
+            }
+            self.buttons.push(ButtonEvent {
+                button,
+                pressed,
+                pos: self.raw_input.mouse_pos,
+            });
+        }
+    }
+
+    pub fn zoom_delta(&self) -> f32 {
+        self.zoom_delta
+    }
+
+    pub fn set_dpi(&mut self, dpi: f32) {
+        self.raw_input.pixels_per_point = Some(dpi);
+        self.update_screen_size();
+        self.request_repaint();
+    }
+
+    /// Re-derives the logical screen size from the physical size and ppp.
+    fn update_screen_size(&mut self) {
+        let ppp = self.ppp();
+        let (w, h) = self.physical_size;
+        self.raw_input.screen_size = vec2(w / ppp, h / ppp);
+    }
+
+    pub fn needs_repaint(&self) -> bool {
+        self.input_since_frame || self.egui_wants_repaint || !self.scroll.is_empty()
+    }
+
+    /// One frame interval after the last frame, so a UI that keeps
+    /// changing runs at no more than 60 frames per second.
+    pub fn next_repaint(&self) -> Option<Instant> {
+        if !self.needs_repaint() {
+            return None;
+        }
+        Some(match self.last_frame {
+            Some(last) => last + FRAME_INTERVAL,
+            None => Instant::now(),
+        })
+    }
+
+    /// Runs the UI state for one frame and returns what egui painted.
+    pub fn run_frame(&mut self) -> (FrameOutput<S::Action>, PaintJobs) {
+        self.last_frame = Some(Instant::now());
+        self.zoom_delta = 1.;
+        self.raw_input.time = self.clock.now();
+        self.raw_input.scroll_delta = self.scroll.take(&self.scroll_settings);
+        let buttons = std::mem::take(&mut self.buttons);
+        self.state.buttons(&buttons);
+        let mut ui = self.ctx.begin_frame(self.raw_input.take());
+        let action = self.state.draw(&mut ui);
+
+        let (output, jobs) = self.ctx.end_frame();
+
+        let mut output = FrameOutput::new(output, action);
+        output.needs_repaint |= !self.scroll.is_empty();
+        self.egui_wants_repaint = output.needs_repaint;
+        self.input_since_frame = false;
+        if let Some(text) = &output.copied_text {
+            self.clipboard.set(text.clone());
+        }
+        for synth in self.touches.end_frame() {
+            self.apply_touch(synth);
+            self.input_since_frame = true;
+        }
+        (output, jobs)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Key, TouchPhase};
+    use egui::Ui;
+
+    /// Remembers the input of the last frame.
+    #[derive(Default)]
+    struct Recorder {
+        events: Vec<egui::Event>,
+        buttons: Vec<ButtonEvent>,
+        mouse_down: bool,
+    }
+
+    impl UiState for Recorder {
+        type Action = ();
+
+        fn draw(&mut self, ui: &mut Ui) -> Option<()> {
+            self.events = ui.input().events.clone();
+            self.mouse_down = ui.input().mouse.down;
+            None
+        }
+
+        fn buttons(&mut self, events: &[ButtonEvent]) {
+            self.buttons = events.to_vec();
+        }
+    }
+
+    /// Keeps asking for frames, like an animation would.
+    struct Animating;
+
+    impl UiState for Animating {
+        type Action = ();
+
+        fn draw(&mut self, ui: &mut Ui) -> Option<()> {
+            ui.ctx().request_repaint();
+            None
+        }
+    }
+
+    fn frontend() -> Frontend<Recorder> {
+        Frontend::new(Recorder::default(), (200., 100.), 1.)
+    }
+
+    #[test]
+    fn ctrl_v_pastes_the_clipboard_as_text() {
+        let mut ui = frontend();
+        ui.clipboard = Box::new(MemoryClipboard {
+            contents: Some("pasted".into()),
+        });
+        let ctrl = Modifiers {
+            ctrl: true,
+            logo: true,
+            ..Default::default()
+        };
+        ui.consume(EventBridge::Modifiers(ctrl));
+        ui.consume(EventBridge::Key {
+            key: Key::V,
+            pressed: true,
+        });
+        ui.run_frame();
+        assert_eq!(ui.state.events, vec![egui::Event::Text("pasted".into())]);
+    }
+
+    fn touch(id: u64, phase: TouchPhase) -> EventBridge {
+        EventBridge::Touch {
+            id,
+            phase,
+            x: 10.,
+            y: 20.,
+        }
+    }
+
+    #[test]
+    fn a_tap_leaves_the_frame_after_it_clicked() {
+        let mut ui = frontend();
+        ui.consume(touch(1, TouchPhase::Start));
+        ui.run_frame();
+        ui.consume(touch(1, TouchPhase::End));
+        assert_eq!(ui.raw_input.mouse_pos, Some(pos2(10., 20.)));
+
+        ui.run_frame();
+        assert_eq!(ui.raw_input.mouse_pos, None);
+        assert!(ui.needs_repaint());
+    }
+
+    #[test]
+    fn losing_focus_forgets_held_fingers() {
+        let mut ui = frontend();
+        ui.consume(touch(1, TouchPhase::Start));
+        ui.consume(EventBridge::Focus(false));
+        assert!(!ui.raw_input.mouse_down);
+
+        // a new touch is a single finger again, not a pinch
+        ui.consume(touch(2, TouchPhase::Start));
+        assert!(ui.raw_input.mouse_down);
+        assert_eq!(ui.raw_input.mouse_pos, Some(pos2(10., 20.)));
+    }
+
+    #[test]
+    fn losing_focus_releases_held_buttons() {
+        let mut ui = frontend();
+        let down = |button| EventBridge::MouseDown {
+            button,
+            pos: Some((30., 40.)),
+        };
+        ui.consume(down(MouseButton::Secondary));
+        ui.consume(down(MouseButton::Middle));
+        ui.consume(EventBridge::MouseUp {
+            button: MouseButton::Middle,
+            pos: None,
+        });
+        ui.run_frame();
+
+        ui.consume(EventBridge::Focus(false));
+        ui.run_frame();
+        assert_eq!(
+            ui.state.buttons,
+            vec![ButtonEvent {
+                button: MouseButton::Secondary,
+                pressed: false,
+                pos: Some(pos2(30., 40.)),
+            }]
+        );
+
+        // released once only
+        ui.consume(EventBridge::Focus(false));
+        ui.run_frame();
+        assert!(ui.state.buttons.is_empty());
+    }
+
+    #[test]
+    fn other_buttons_reach_the_state_instead_of_egui() {
+        let mut ui = frontend();
+        ui.consume(EventBridge::MouseDown {
+            button: MouseButton::Secondary,
+            pos: Some((30., 40.)),
+        });
+        ui.run_frame();
+        assert!(!ui.state.mouse_down);
+        assert_eq!(
+            ui.state.buttons,
+            vec![ButtonEvent {
+                button: MouseButton::Secondary,
+                pressed: true,
+                pos: Some(pos2(30., 40.)),
+            }]
+        );
+
+        // delivered once only
+        ui.run_frame();
+        assert!(ui.state.buttons.is_empty());
+    }
+
+    #[test]
+    fn primary_button_presses_egui() {
+        let mut ui = frontend();
+        ui.consume(EventBridge::MouseDown {
+            button: MouseButton::Primary,
+            pos: Some((30., 40.)),
+        });
+        ui.run_frame();
+        assert!(ui.state.mouse_down);
+        assert!(ui.state.buttons.is_empty());
+    }
+
+    #[test]
+    fn idle_after_a_frame_until_input_arrives() {
+        let mut ui = frontend();
+        assert!(ui.needs_repaint());
+        ui.run_frame();
+        assert!(!ui.needs_repaint());
+        assert_eq!(ui.next_repaint(), None);
+
+        ui.consume(EventBridge::Ignore);
+        assert!(!ui.needs_repaint());
+
+        ui.consume(EventBridge::MouseMove { x: 1., y: 2. });
+        assert!(ui.needs_repaint());
+    }
+
+    #[test]
+    fn host_changes_ask_for_a_frame() {
+        let mut ui = frontend();
+        ui.run_frame();
+        ui.state_mut().mouse_down = true;
+        assert!(ui.needs_repaint());
+
+        ui.run_frame();
+        ui.set_dpi(2.);
+        assert!(ui.needs_repaint());
+    }
+
+    #[test]
+    fn repaints_are_a_frame_apart() {
+        let mut ui = frontend();
+        let before = Instant::now();
+        ui.run_frame();
+        ui.consume(EventBridge::MouseMove { x: 1., y: 2. });
+        let next = ui.next_repaint().unwrap();
+        assert!(next >= before + FRAME_INTERVAL);
+        assert!(next <= Instant::now() + FRAME_INTERVAL);
+    }
+
+    #[test]
+    fn egui_and_smooth_scrolling_keep_repainting() {
+        let mut animating = Frontend::new(Animating, (200., 100.), 1.);
+        animating.run_frame();
+        assert!(animating.needs_repaint());
+
+        let mut ui = frontend();
+        ui.scroll_settings.smoothing = Some(0.5);
+        ui.consume(EventBridge::Scroll { x: 0., y: 100. });
+        ui.run_frame();
+        assert!(ui.needs_repaint());
+    }
+}
@@ -0,0 +1,176 @@
+use egui::Event;
+
+/// Keys the UI understands, independent of any windowing library.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Key {
+    Alt,
+    Backspace,
+    Control,
+    Delete,
+    Down,
+    End,
+    Enter,
+    Escape,
+    Home,
+    Insert,
+    Left,
+    Logo,
+    PageDown,
+    PageUp,
+    Right,
+    Shift,
+    Tab,
+    Up,
+    C,
+    V,
+    X,
+    /// Any key without a meaning to egui.
+    Other,
+}
+
+/// Mouse buttons, independent of any windowing library. egui 0.1 only
+/// reacts to `Primary`, the others are handed to
+/// [`UiState::buttons`](crate::UiState::buttons).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MouseButton {
+    Primary,
+    Secondary,
+    Middle,
+    Extra(u8),
+}
+
+/// A press or release of a button egui does not handle.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ButtonEvent {
+    pub button: MouseButton,
+    pub pressed: bool,
+    /// Pointer position in points, if it is over the window.
+    pub pos: Option<egui::Pos2>,
+}
+
+/// Modifier keys held down.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Modifiers {
+    pub alt: bool,
+    pub ctrl: bool,
+    pub shift: bool,
+    /// The windows key, or command on mac.
+    pub logo: bool,
+}
+
+impl Modifiers {
+    /// The platform's shortcut modifier.
+    pub fn command(&self) -> bool {
+        if cfg!(target_os = "macos") {
+            self.logo
+        } else {
+            self.ctrl
+        }
+    }
+}
+
+impl Key {
+    fn to_egui(self) -> Option<egui::Key> {
+        Some(match self {
+            Key::Alt => egui::Key::Alt,
+            Key::Backspace => egui::Key::Backspace,
+            Key::Control => egui::Key::Control,
+            Key::Delete => egui::Key::Delete,
+            Key::Down => egui::Key::Down,
+            Key::End => egui::Key::End,
+            Key::Enter => egui::Key::Enter,
+            Key::Escape => egui::Key::Escape,
+            Key::Home => egui::Key::Home,
+            Key::Insert => egui::Key::Insert,
+            Key::Left => egui::Key::Left,
+            Key::Logo => egui::Key::Logo,
+            Key::PageDown => egui::Key::PageDown,
+            Key::PageUp => egui::Key::PageUp,
+            Key::Right => egui::Key::Right,
+            Key::Shift => egui::Key::Shift,
+            Key::Tab => egui::Key::Tab,
+            Key::Up => egui::Key::Up,
+            _ => return None,
+        })
+    }
+}
+
+/// Translates a key press or release into egui events, turning the copy
+/// and cut shortcuts into their own events.
+pub fn key_events(key: Key, pressed: bool, modifiers: Modifiers) -> Vec<Event> {
+    if pressed && modifiers.command() {
+        match key {
+            Key::C => return vec![Event::Copy],
+            Key::X => return vec![Event::Cut],
+            _ => {}
+        }
+    }
+    key.to_egui()
+        .map(|key| Event::Key { key, pressed })
+        .into_iter()
+        .collect()
+}
+
+/// Whether this is the paste shortcut, which needs the clipboard's
+/// contents to turn into an event.
+pub fn is_paste(key: Key, pressed: bool, modifiers: Modifiers) -> bool {
+    pressed && key == Key::V && modifiers.command()
+}
+
+/// Typed characters become text, unless they are control characters or
+/// part of a shortcut.
+pub fn text_event(c: char, modifiers: Modifiers) -> Option<Event> {
+    if c.is_control() || modifiers.ctrl || modifiers.logo {
+        None
+    } else {
+        Some(Event::Text(c.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn ctrl() -> Modifiers {
+        Modifiers {
+            ctrl: true,
+            logo: true,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn shortcuts_become_clipboard_events() {
+        assert_eq!(key_events(Key::C, true, ctrl()), vec![Event::Copy]);
+        assert_eq!(key_events(Key::X, true, ctrl()), vec![Event::Cut]);
+        assert!(key_events(Key::C, true, Modifiers::default()).is_empty());
+    }
+
+    #[test]
+    fn paste_needs_the_command_modifier() {
+        assert!(is_paste(Key::V, true, ctrl()));
+        assert!(!is_paste(Key::V, false, ctrl()));
+        assert!(!is_paste(Key::V, true, Modifiers::default()));
+    }
+
+    #[test]
+    fn named_keys_are_forwarded() {
+        assert_eq!(
+            key_events(Key::Backspace, false, Modifiers::default()),
+            vec![Event::Key {
+                key: egui::Key::Backspace,
+                pressed: false
+            }]
+        );
+    }
+
+    #[test]
+    fn control_characters_are_not_text() {
+        assert_eq!(text_event('\u{8}', Modifiers::default()), None);
+        assert_eq!(text_event('c', ctrl()), None);
+        assert_eq!(
+            text_event('c', Modifiers::default()),
+            Some(Event::Text("c".into()))
+        );
+    }
+}
@@ -1,12 +1,44 @@
+mod buffer;
+mod capture;
+mod clipboard;
+mod clock;
+mod color_space;
+mod frontend;
+mod input;
+mod mesh;
+mod msaa;
+mod output;
 mod pipeline;
+mod scissor;
+mod scroll;
 mod shaders;
+pub mod software;
+#[cfg(feature = "testing")]
+pub mod testing;
+mod touch;
+#[cfg(feature = "winit")]
+pub mod winit;
 
+use buffer::*;
 use bytemuck::{cast_slice, Pod, Zeroable};
-use egui::{paint, pos2, vec2, Context, RawInput, Ui};
+pub use capture::RgbaImage;
+pub use clipboard::{Clipboard, MemoryClipboard};
+pub use clock::{Clock, ManualClock, SystemClock};
+use color_space::assert_hdr_applies;
+pub use color_space::{HdrSettings, OutputColorSpace};
+use egui::{paint, Ui};
+use frontend::Frontend;
+pub use input::{ButtonEvent, Key, Modifiers, MouseButton};
+use mesh::Mesh;
+use msaa::{assert_loadable, MsaaTarget};
+pub use output::FrameOutput;
 use pipeline::*;
+use scissor::scissor_rect;
+pub use scroll::ScrollSettings;
 use shaders::*;
+use std::collections::HashMap;
 use std::mem::size_of;
-use std::sync::Arc;
+pub use touch::TouchPhase;
 use wgpu::*;
 
 #[derive(Copy, Clone)]
@@ -35,29 +67,130 @@ impl From<paint::Vertex> for V {
 }
 
 /// All events you pass to the UI state should be
-/// convertable to this type.
-#[derive(Debug, Clone, Copy)]
+/// convertable to this type. Positions are in physical pixels, a button
+/// event without one keeps the last `MouseMove` position. `Modifiers`
+/// carries the full modifier state and should be sent whenever it
+/// changes, `Char` is a typed character. `Focus(false)` releases any held
+/// buttons, fingers and modifiers.
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum EventBridge {
-    MouseMove { x: f32, y: f32 },
-    MouseDown,
-    MouseUp,
-    Scroll { x: f32, y: f32 },
-    Resize { w: f32, h: f32 },
+    MouseMove {
+        x: f32,
+        y: f32,
+    },
+    MouseDown {
+        button: MouseButton,
+        pos: Option<(f32, f32)>,
+    },
+    MouseUp {
+        button: MouseButton,
+        pos: Option<(f32, f32)>,
+    },
+    Scroll {
+        x: f32,
+        y: f32,
+    },
+    ScrollLines {
+        x: f32,
+        y: f32,
+    },
+    Resize {
+        w: f32,
+        h: f32,
+    },
     PppChanged(f32),
+    Key {
+        key: Key,
+        pressed: bool,
+    },
+    Modifiers(Modifiers),
+    Char(char),
+    Touch {
+        id: u64,
+        phase: TouchPhase,
+        x: f32,
+        y: f32,
+    },
+    PointerLeft,
+    Focus(bool),
     Ignore,
 }
 
+/// Identifies the texture a paint job samples from. Mirrors the
+/// `TextureId` of later egui releases, egui 0.1 has none of its own.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TextureId {
+    /// The font atlas egui manages itself.
+    Egui,
+    /// A texture registered with [`EguiRenderer::register_texture`].
+    User(u64),
+}
+
+/// A rectangle showing a registered texture, e.g. an image, a video
+/// frame or a render target preview. Colors are premultiplied sRGB, as
+/// everywhere in egui.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct UserImage {
+    pub texture: TextureId,
+    /// Where the image goes, in points.
+    pub rect: egui::Rect,
+    /// The part of the texture shown, from `(0, 0)` to `(1, 1)`.
+    pub uv: egui::Rect,
+    /// Nothing outside of it is drawn, usually `ui.clip_rect()`.
+    pub clip: egui::Rect,
+    /// Multiplied with the texture.
+    pub tint: paint::Srgba,
+}
+
+impl UserImage {
+    /// Shows all of `texture` untinted.
+    pub fn new(texture: TextureId, rect: egui::Rect, clip: egui::Rect) -> Self {
+        Self {
+            texture,
+            rect,
+            uv: egui::Rect::from_min_max(egui::pos2(0., 0.), egui::pos2(1., 1.)),
+            clip,
+            tint: paint::color::WHITE,
+        }
+    }
+}
+
 pub trait UiState {
+    /// Handed back to the host from a frame, use `()` if the UI has
+    /// nothing to report.
+    type Action;
+
     /// Place Drawing Logic Here
-    fn draw(&self, ui: &mut Ui);
+    fn draw(&mut self, ui: &mut Ui) -> Option<Self::Action>;
+
+    /// Presses and releases of every button but the primary one since the
+    /// last frame, which egui 0.1 cannot handle. Called right before
+    /// `draw`, e.g. to open a context menu where the secondary button
+    /// went down.
+    fn buttons(&mut self, _events: &[ButtonEvent]) {}
+
+    /// Images to draw with registered textures this frame. Called right
+    /// after `draw`, so the space for them can be allocated there, e.g.
+    /// with `ui.allocate_space`. egui 0.1 meshes cannot refer to other
+    /// textures, so these are drawn on top of everything egui painted.
+    fn user_images(&mut self) -> Vec<UserImage> {
+        Vec::new()
+    }
 }
 
 pub struct EguiRenderer<S: UiState> {
     ui_pl: Pipeline,
-    raw_input: RawInput,
-    ctx: Arc<Context>,
-    state: S,
-    start_time: std::time::Instant,
+    mesh: Mesh,
+    vert_buf: GrowableBuffer,
+    idx_buf: GrowableBuffer,
+    reallocations: usize,
+    user_textures: HashMap<u64, BindGroup>,
+    next_user_texture: u64,
+    ui: Frontend<S>,
+    fmt: TextureFormat,
+    load: LoadOp<Color>,
+    sample_count: u32,
+    msaa: MsaaTarget,
 }
 
 pub struct EguiRendererDescriptor<S: UiState> {
@@ -65,6 +198,25 @@ pub struct EguiRendererDescriptor<S: UiState> {
     pub fmt: TextureFormat,
     pub screen_size: (f32, f32),
     pub ppp: f32,
+    /// What the renderer-owned pass does with the target first:
+    /// `Clear(Color::TRANSPARENT)` for a layer that is composited later,
+    /// `Load` to draw over what is already there.
+    pub load: LoadOp<Color>,
+    /// Overrides the color space detected from `fmt`, for targets that
+    /// are reinterpreted through a view of another format.
+    pub color_space: Option<OutputColorSpace>,
+    /// Scales the UI to a paper white level for float targets holding
+    /// HDR content. `None` leaves white at 1.0. Panics if the color space
+    /// is [`OutputColorSpace::Gamma`].
+    pub hdr: Option<HdrSettings>,
+    /// Samples per pixel of the passes the UI is drawn in, 1 disables
+    /// multisampling. Passes given to [`EguiRenderer::render`] must use
+    /// the same count. The renderer-owned pass draws into an internal
+    /// multisampled texture resolved into the output view, which cannot
+    /// load the view's contents, so `Load` panics with multisampling.
+    /// Draw overlays with [`EguiRenderer::render`] in your own
+    /// multisampled pass instead.
+    pub sample_count: u32,
 }
 
 impl<S> EguiRenderer<S>
@@ -78,144 +230,319 @@ where
             screen_size,
             state,
             ppp,
+            load,
+            color_space,
+            hdr,
+            sample_count,
         } = desc;
 
-        let mut ctx = Context::new();
-        let raw_input = RawInput {
-            pixels_per_point: Some(ppp),
-            screen_size: vec2(screen_size.0 / ppp, screen_size.1 / ppp),
-            ..Default::default()
-        };
-        let _ = ctx.begin_frame(raw_input.clone());
+        let space = color_space.unwrap_or_else(|| OutputColorSpace::detect(fmt));
+        assert_hdr_applies(space, hdr);
+        assert_loadable(load, sample_count);
+        let ui = Frontend::new(state, screen_size, ppp);
 
         let ui_pl = Pipeline::new(
             dev,
             queue,
-            ctx.texture(),
-            fmt,
-            (screen_size.0 / ppp, screen_size.1 / ppp),
+            ui.ctx.texture(),
+            PipelineDescriptor {
+                fmt,
+                space,
+                hdr,
+                sample_count,
+                screen_dims: (screen_size.0 / ppp, screen_size.1 / ppp),
+            },
         );
 
         Self {
             ui_pl,
-            ctx,
-            state,
-            raw_input,
-            start_time: std::time::Instant::now(),
+            mesh: Mesh::default(),
+            vert_buf: GrowableBuffer::new(
+                dev,
+                "egui-wgpu :: vertex_buffer",
+                BufferUsage::VERTEX,
+                size_of::<V>() as u64 * 1024,
+            ),
+            idx_buf: GrowableBuffer::new(
+                dev,
+                "egui-wgpu :: index_buffer",
+                BufferUsage::INDEX,
+                size_of::<u32>() as u64 * 1024 * 3,
+            ),
+            reallocations: 0,
+            user_textures: HashMap::new(),
+            next_user_texture: 0,
+            ui,
+            fmt,
+            load,
+            sample_count,
+            msaa: MsaaTarget::default(),
         }
     }
 
     /// this should be called in a loop in immediate mode
-    pub fn consume_event<'a, T>(&mut self, input: T)
+    pub fn consume_event<T>(&mut self, input: T)
     where
         T: Into<EventBridge>,
     {
-        self.raw_input.time = self.start_time.elapsed().as_nanos() as f64 * 1e-9;
-        let ppp = self.raw_input.pixels_per_point.unwrap_or(1.);
-
-        match input.into() {
-            EventBridge::MouseUp => self.raw_input.mouse_down = false,
-            EventBridge::MouseDown => self.raw_input.mouse_down = true,
-            EventBridge::Scroll { x, y } => self.raw_input.scroll_delta = vec2(x, y),
-            EventBridge::MouseMove { x, y } => {
-                self.raw_input.mouse_pos = Some(pos2(x / ppp, y / ppp))
-            }
-            EventBridge::Resize { w, h } => {
-                self.raw_input.screen_size = vec2(w, h);
-                self.ui_pl.resize(w, h);
+        self.ui.consume(input.into());
+    }
+
+    /// How much pinch gestures zoomed since the last frame, as a factor.
+    /// egui 0.1 has no zoom input of its own, so it is up to the host
+    /// to apply it.
+    pub fn zoom_delta(&self) -> f32 {
+        self.ui.zoom_delta()
+    }
+
+    pub fn state(&self) -> &S {
+        &self.ui.state
+    }
+
+    /// Changes made here show up in the next frame.
+    pub fn state_mut(&mut self) -> &mut S {
+        self.ui.state_mut()
+    }
+
+    /// Whether anything changed since the last frame: input arrived, the
+    /// state, a texture or the load op was changed through the renderer,
+    /// egui is animating or smooth scrolling is still under way. Hosts
+    /// can sleep until this becomes true.
+    pub fn needs_repaint(&self) -> bool {
+        self.ui.needs_repaint()
+    }
+
+    /// When the next frame should be drawn: a frame interval after the
+    /// last one while anything changes, `None` if the UI is idle and only
+    /// new input can change it.
+    pub fn next_repaint(&self) -> Option<std::time::Instant> {
+        self.ui.next_repaint()
+    }
+
+    /// Replaces the wall clock, e.g. with a [`ManualClock`] to make
+    /// animations and double clicks deterministic.
+    pub fn set_clock<C: Clock + 'static>(&mut self, clock: C) {
+        self.ui.clock = Box::new(clock);
+    }
+
+    /// Replaces the in-memory clipboard with the host's.
+    pub fn set_clipboard<C: Clipboard + 'static>(&mut self, clipboard: C) {
+        self.ui.clipboard = Box::new(clipboard);
+    }
+
+    /// Panics if the settings are invalid, see [`ScrollSettings`].
+    pub fn set_scroll_settings(&mut self, settings: ScrollSettings) {
+        settings.validate();
+        self.ui.scroll_settings = settings;
+    }
+
+    pub fn set_dpi(&mut self, dpi: f32) {
+        self.ui.set_dpi(dpi);
+    }
+
+    /// Runs the UI for one frame, tessellates it and uploads the
+    /// resulting geometry. Call this before [`EguiRenderer::render`].
+    pub fn prepare(&mut self, dev: &Device, queue: &Queue) -> FrameOutput<S::Action> {
+        let (w, h) = self.ui.screen_size();
+        self.ui_pl.resize(queue, w, h);
+
+        let (output, jobs) = self.ui.run_frame();
+
+        if self.ui.ctx.texture().id != self.ui_pl.tex_hash {
+            self.ui_pl.rebuild_texture(dev, queue, self.ui.ctx.as_ref());
+        }
+
+        let images = self.ui.state.user_images();
+        let tex = self.ui.ctx.texture();
+        self.mesh = Mesh::build(jobs, &images, (tex.width, tex.height));
+
+        if self
+            .vert_buf
+            .write(dev, queue, cast_slice(&self.mesh.verts))
+        {
+            self.reallocations += 1;
+        }
+        if self.idx_buf.write(dev, queue, cast_slice(&self.mesh.idxs)) {
+            self.reallocations += 1;
+        }
+        output
+    }
+
+    /// The number of times the vertex or index buffer had to be grown.
+    /// Once the UI reaches a steady state this stops increasing.
+    pub fn buffer_reallocations(&self) -> usize {
+        self.reallocations
+    }
+
+    /// Makes `view` available to the UI, sampled with `sampler` or with
+    /// the font atlas sampler if none is given.
+    pub fn register_texture(
+        &mut self,
+        dev: &Device,
+        view: &TextureView,
+        sampler: Option<&Sampler>,
+    ) -> TextureId {
+        let id = self.next_user_texture;
+        self.next_user_texture += 1;
+        self.user_textures
+            .insert(id, self.ui_pl.texture_bg(dev, view, sampler));
+        self.ui.request_repaint();
+        TextureId::User(id)
+    }
+
+    /// Points a registered texture at a new view, e.g. after the
+    /// underlying texture was recreated at a different size.
+    pub fn update_texture(
+        &mut self,
+        dev: &Device,
+        id: TextureId,
+        view: &TextureView,
+        sampler: Option<&Sampler>,
+    ) {
+        if let TextureId::User(id) = id {
+            if let Some(bg) = self.user_textures.get_mut(&id) {
+                *bg = self.ui_pl.texture_bg(dev, view, sampler);
+                self.ui.request_repaint();
             }
-            EventBridge::PppChanged(dpi) => self.raw_input.pixels_per_point = Some(dpi),
-            _ => {}
         }
     }
 
-    pub fn set_dpi(&mut self, dpi: f32) {
-        self.raw_input.pixels_per_point = Some(dpi);
+    /// Releases a registered texture, paint jobs still using it are skipped.
+    pub fn free_texture(&mut self, id: TextureId) {
+        if let TextureId::User(id) = id {
+            self.user_textures.remove(&id);
+            self.ui.request_repaint();
+        }
+    }
+
+    fn bind_group(&self, id: TextureId) -> Option<&BindGroup> {
+        match id {
+            TextureId::Egui => Some(&self.ui_pl.frag_bg),
+            TextureId::User(id) => self.user_textures.get(&id),
+        }
     }
 
-    /// Draws the UI using `render_pass`.
+    /// Records the geometry uploaded by the last call to
+    /// [`EguiRenderer::prepare`] into a render pass the caller owns.
+    /// Nothing is cleared and nothing is submitted. `target` is the size
+    /// of the pass's attachments in physical pixels, scissors are clamped
+    /// to it.
+    pub fn render<'rp>(&'rp self, rpass: &mut RenderPass<'rp>, target: (u32, u32)) {
+        let ppp = self.ui.ppp();
+        rpass.set_pipeline(&self.ui_pl.pl);
+        rpass.set_bind_group(0, &self.ui_pl.vert_bg, &[]);
+
+        // an empty slice would bind the rest of the buffer, so skip it.
+        let calls = self.mesh.calls.iter().filter(|call| call.idx_count > 0);
+        calls.for_each(|call| {
+            let bg = match self.bind_group(call.texture) {
+                Some(bg) => bg,
+                None => return,
+            };
+            let (x, y, w, h) = match scissor_rect(call.clip, ppp, target) {
+                Some(rect) => rect,
+                None => return,
+            };
+            rpass.set_bind_group(1, bg, &[]);
+            rpass.set_scissor_rect(x, y, w, h);
+            rpass.set_vertex_buffer(0, self.vert_buf.buf.slice(call.verts.clone()));
+            rpass.set_index_buffer(self.idx_buf.buf.slice(call.idxs.clone()));
+            rpass.draw_indexed(0..call.idx_count, 0, 0..1);
+        });
+    }
+
+    /// Draws the UI onto `frame` in a pass of its own, starting with the
+    /// descriptor's load op, and submits `com`. Use
+    /// [`EguiRenderer::prepare`] and [`EguiRenderer::render`] to composite
+    /// the UI over your own scene.
     pub fn draw_on(
         &mut self,
-        mut com: CommandEncoder,
+        com: CommandEncoder,
         dev: &Device,
         queue: &Queue,
         frame: SwapChainFrame,
-    ) {
-        {
-            // render the scene
-            let mut ui = self.ctx.begin_frame(self.raw_input.take());
-            self.state.draw(&mut ui);
+    ) -> FrameOutput<S::Action> {
+        let load = self.load;
+        self.draw_on_with(com, dev, queue, frame, load)
+    }
 
-            let (_, jobs) = self.ctx.end_frame();
+    /// [`EguiRenderer::draw_on`] with a load op for this frame only.
+    pub fn draw_on_with(
+        &mut self,
+        com: CommandEncoder,
+        dev: &Device,
+        queue: &Queue,
+        frame: SwapChainFrame,
+        load: LoadOp<Color>,
+    ) -> FrameOutput<S::Action> {
+        let size = self.target_size();
+        self.draw_to_view_with(com, dev, queue, &frame.output.view, size, load)
+    }
 
-            if self.ctx.texture().id != self.ui_pl.tex_hash {
-                self.ui_pl.rebuild_texture(&queue, self.ctx.as_ref());
-            }
+    /// Like [`EguiRenderer::draw_on`] but targets any view of a texture
+    /// with the format the renderer was created with, no window needed.
+    /// The texture must have `OUTPUT_ATTACHMENT` usage, `size` is its size
+    /// in physical pixels.
+    pub fn draw_to_view(
+        &mut self,
+        com: CommandEncoder,
+        dev: &Device,
+        queue: &Queue,
+        view: &TextureView,
+        size: (u32, u32),
+    ) -> FrameOutput<S::Action> {
+        let load = self.load;
+        self.draw_to_view_with(com, dev, queue, view, size, load)
+    }
 
-            let buffers: Vec<_> = jobs
-                .into_iter()
-                .map(|(egui::Rect { min, max }, triangles)| {
-                    let vert_buf = dev.create_buffer(&BufferDescriptor {
-                        label: Some("egui-wgpu :: vertex_buffer "),
-                        size: size_of::<V>() as u64 * triangles.vertices.len() as u64,
-                        usage: BufferUsage::VERTEX | BufferUsage::COPY_DST,
-                        mapped_at_creation: true,
-                    });
-
-                    let idx_buf = dev.create_buffer(&BufferDescriptor {
-                        label: Some("egui-wgpu :: index_buffer "),
-                        size: size_of::<u32>() as u64 * triangles.indices.len() as u64,
-                        usage: BufferUsage::INDEX | BufferUsage::COPY_DST,
-                        mapped_at_creation: true,
-                    });
-
-                    {
-                        let mut idx = idx_buf.slice(..).get_mapped_range_mut();
-                        idx.copy_from_slice(cast_slice(&triangles.indices));
-                    }
-                    idx_buf.unmap();
-
-                    {
-                        let mut vtx = vert_buf.slice(..).get_mapped_range_mut();
-                        let verts: Vec<_> = triangles.vertices.into_iter().map(V::from).collect();
-                        vtx.copy_from_slice(cast_slice(&verts));
-                    }
-                    vert_buf.unmap();
-
-                    (
-                        vert_buf,
-                        idx_buf,
-                        triangles.indices.len(),
-                        (min.x, min.y, max.x, max.y),
-                    )
-                })
-                .collect();
+    /// [`EguiRenderer::draw_to_view`] with a load op for this frame only.
+    pub fn draw_to_view_with(
+        &mut self,
+        mut com: CommandEncoder,
+        dev: &Device,
+        queue: &Queue,
+        view: &TextureView,
+        size: (u32, u32),
+        load: LoadOp<Color>,
+    ) -> FrameOutput<S::Action> {
+        assert_loadable(load, self.sample_count);
+        let output = self.prepare(dev, queue);
+        if self.sample_count > 1 {
+            let (fmt, samples) = (self.fmt, self.sample_count);
+            self.msaa
+                .fit(size, |size| msaa::create_view(dev, fmt, samples, size));
+        }
 
+        {
+            let (attachment, resolve_target) = match self.msaa.view() {
+                Some(msaa) => (msaa, Some(view)),
+                None => (view, None),
+            };
             let mut rpass = com.begin_render_pass(&wgpu::RenderPassDescriptor {
                 color_attachments: &[wgpu::RenderPassColorAttachmentDescriptor {
-                    attachment: &frame.output.view,
-                    resolve_target: None,
-                    ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(Color::BLACK),
-                        store: true,
-                    },
+                    attachment,
+                    resolve_target,
+                    ops: wgpu::Operations { load, store: true },
                 }],
                 depth_stencil_attachment: None,
             });
-
-            let ppp = self.raw_input.pixels_per_point.unwrap();
-            rpass.set_pipeline(&self.ui_pl.pl);
-            rpass.set_bind_group(0, &self.ui_pl.vert_bg, &[]);
-            rpass.set_bind_group(1, &self.ui_pl.frag_bg, &[]);
-
-            buffers.iter().for_each(|(v, i, ct, (x, y, w, h))| {
-                rpass.set_scissor_rect(*x as u32, *y as u32, (w * ppp) as u32, (h * ppp) as u32);
-                rpass.set_vertex_buffer(0, v.slice(..));
-                rpass.set_index_buffer(i.slice(..));
-                rpass.draw_indexed(0..*ct as u32, 0, 0..1);
-            });
+            self.render(&mut rpass, size);
         }
 
         queue.submit(Some(com.finish()));
+        output
+    }
+
+    /// Size of the window in physical pixels.
+    fn target_size(&self) -> (u32, u32) {
+        let (w, h) = self.ui.physical_size;
+        (w.round() as u32, h.round() as u32)
+    }
+
+    /// Sets the load op used by the renderer-owned pass from now on.
+    pub fn set_load_op(&mut self, load: LoadOp<Color>) {
+        assert_loadable(load, self.sample_count);
+        self.load = load;
+        self.ui.request_repaint();
     }
 }
@@ -0,0 +1,164 @@
+use crate::{TextureId, UserImage, V};
+use egui::paint::{PaintJobs, Srgba};
+use std::{mem::size_of, ops::Range};
+use wgpu::BufferAddress;
+
+/// Byte ranges of a single paint job inside the shared
+/// vertex and index buffers.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DrawCall {
+    pub verts: Range<BufferAddress>,
+    pub idxs: Range<BufferAddress>,
+    pub idx_count: u32,
+    pub texture: TextureId,
+    pub clip: (f32, f32, f32, f32),
+}
+
+impl DrawCall {
+    /// The call's vertices as indices into [`Mesh::verts`].
+    pub fn vert_range(&self) -> Range<usize> {
+        let size = size_of::<V>() as BufferAddress;
+        (self.verts.start / size) as usize..(self.verts.end / size) as usize
+    }
+
+    /// The call's indices as indices into [`Mesh::idxs`].
+    pub fn idx_range(&self) -> Range<usize> {
+        let size = size_of::<u32>() as BufferAddress;
+        (self.idxs.start / size) as usize..(self.idxs.end / size) as usize
+    }
+}
+
+/// The geometry of one frame, ready to be uploaded.
+#[derive(Default)]
+pub struct Mesh {
+    pub verts: Vec<V>,
+    pub idxs: Vec<u32>,
+    pub calls: Vec<DrawCall>,
+}
+
+impl Mesh {
+    /// Collects egui's paint jobs followed by the user images. The shader
+    /// divides texture coordinates by the size of the font atlas, so the
+    /// normalized coordinates of user images are scaled up by it.
+    pub fn build(jobs: PaintJobs, images: &[UserImage], atlas_size: (usize, usize)) -> Self {
+        let mut mesh = Mesh::default();
+        for (clip, triangles) in jobs {
+            let verts = triangles.vertices.into_iter().map(V::from);
+            // egui 0.1 meshes always sample the font atlas, they carry no
+            // texture id of their own.
+            mesh.push(TextureId::Egui, clip, verts, &triangles.indices);
+        }
+
+        let (w, h) = (atlas_size.0 as f32, atlas_size.1 as f32);
+        for image in images {
+            let UserImage {
+                texture,
+                rect,
+                uv,
+                clip,
+                tint: Srgba(srgba),
+            } = *image;
+            let corner = |x: bool, y: bool| V {
+                a_pos: [
+                    if x { rect.max.x } else { rect.min.x },
+                    if y { rect.max.y } else { rect.min.y },
+                ],
+                a_tc: [
+                    if x { uv.max.x } else { uv.min.x } * w,
+                    if y { uv.max.y } else { uv.min.y } * h,
+                ],
+                a_srgba: srgba,
+            };
+            let verts = vec![
+                corner(false, false),
+                corner(true, false),
+                corner(false, true),
+                corner(true, true),
+            ];
+            mesh.push(texture, clip, verts.into_iter(), &[0, 1, 2, 2, 1, 3]);
+        }
+        mesh
+    }
+
+    fn push(
+        &mut self,
+        texture: TextureId,
+        clip: egui::Rect,
+        verts: impl Iterator<Item = V>,
+        idxs: &[u32],
+    ) {
+        let v_start = (self.verts.len() * size_of::<V>()) as BufferAddress;
+        let i_start = (self.idxs.len() * size_of::<u32>()) as BufferAddress;
+        self.verts.extend(verts);
+        self.idxs.extend(idxs);
+
+        self.calls.push(DrawCall {
+            verts: v_start..(self.verts.len() * size_of::<V>()) as BufferAddress,
+            idxs: i_start..(self.idxs.len() * size_of::<u32>()) as BufferAddress,
+            idx_count: idxs.len() as u32,
+            texture,
+            clip: (clip.min.x, clip.min.y, clip.max.x, clip.max.y),
+        });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use egui::{paint::Triangles, pos2, Rect};
+
+    fn rect(x0: f32, y0: f32, x1: f32, y1: f32) -> Rect {
+        Rect::from_min_max(pos2(x0, y0), pos2(x1, y1))
+    }
+
+    fn triangle() -> Triangles {
+        Triangles {
+            indices: vec![0, 1, 2],
+            vertices: vec![Default::default(); 3],
+        }
+    }
+
+    #[test]
+    fn egui_jobs_sample_the_atlas() {
+        let mesh = Mesh::build(vec![(rect(0., 0., 10., 10.), triangle())], &[], (64, 64));
+        assert_eq!(
+            mesh.calls,
+            vec![DrawCall {
+                verts: 0..3 * size_of::<V>() as BufferAddress,
+                idxs: 0..12,
+                idx_count: 3,
+                texture: TextureId::Egui,
+                clip: (0., 0., 10., 10.),
+            }]
+        );
+    }
+
+    #[test]
+    fn user_images_reach_a_draw_call_with_their_texture() {
+        // what register_texture hands out first
+        let texture = TextureId::User(0);
+        let image = UserImage {
+            uv: rect(0., 0.5, 1., 1.),
+            ..UserImage::new(texture, rect(10., 20., 30., 40.), rect(0., 0., 100., 100.))
+        };
+        let jobs = vec![(rect(0., 0., 10., 10.), triangle())];
+        let mesh = Mesh::build(jobs, &[image], (64, 32));
+
+        assert_eq!(mesh.calls.len(), 2);
+        let call = &mesh.calls[1];
+        assert_eq!(call.texture, texture);
+        assert_eq!(call.clip, (0., 0., 100., 100.));
+        assert_eq!(call.idx_count, 6);
+        assert_eq!(call.idxs, 12..36);
+        assert_eq!(call.vert_range(), 3..7);
+        assert_eq!(call.idx_range(), 3..9);
+
+        // indices are relative to the call's own vertex range
+        assert_eq!(&mesh.idxs[3..], &[0, 1, 2, 2, 1, 3]);
+        let last = &mesh.verts[6];
+        assert_eq!(last.a_pos, [30., 40.]);
+        assert_eq!(last.a_tc, [64., 32.]);
+        assert_eq!(mesh.verts[3].a_tc, [0., 16.]);
+        assert_eq!(last.a_srgba, [255; 4]);
+    }
+}
@@ -0,0 +1,101 @@
+use wgpu::*;
+
+/// Multisampled color target the renderer-owned pass draws into before
+/// resolving to the caller's view. Generic over the view so the resizing
+/// can be tested without a device.
+pub struct MsaaTarget<V = TextureView> {
+    current: Option<((u32, u32), V)>,
+}
+
+impl<V> Default for MsaaTarget<V> {
+    fn default() -> Self {
+        Self { current: None }
+    }
+}
+
+impl<V> MsaaTarget<V> {
+    /// Makes sure the target matches a view of `size`, calling `create`
+    /// only when there is none yet or the size changed.
+    pub fn fit(&mut self, size: (u32, u32), create: impl FnOnce((u32, u32)) -> V) {
+        match &self.current {
+            Some((current, _)) if *current == size => {}
+            _ => self.current = Some((size, create(size))),
+        }
+    }
+
+    pub fn view(&self) -> Option<&V> {
+        self.current.as_ref().map(|(_, view)| view)
+    }
+}
+
+pub fn create_view(
+    dev: &Device,
+    fmt: TextureFormat,
+    sample_count: u32,
+    size: (u32, u32),
+) -> TextureView {
+    let texture = dev.create_texture(&TextureDescriptor {
+        label: Some("egui-wgpu :: msaa_texture"),
+        // a minimized window still needs a valid texture
+        size: Extent3d {
+            width: size.0.max(1),
+            height: size.1.max(1),
+            depth: 1,
+        },
+        mip_level_count: 1,
+        sample_count,
+        dimension: TextureDimension::D2,
+        format: fmt,
+        usage: TextureUsage::OUTPUT_ATTACHMENT,
+    });
+    texture.create_view(&Default::default())
+}
+
+/// Panics for `Load` with multisampling. The renderer-owned pass draws
+/// into its own multisampled texture, which never holds the view's
+/// contents, so there is nothing to load.
+pub fn assert_loadable(load: LoadOp<Color>, sample_count: u32) {
+    assert!(
+        sample_count == 1 || !matches!(load, LoadOp::Load),
+        "LoadOp::Load needs a sample_count of 1, draw overlays with \
+         EguiRenderer::render in your own multisampled pass instead"
+    );
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn recreated_only_when_resized() {
+        let mut target = MsaaTarget::default();
+        let mut created = 0;
+        let mut fit = |target: &mut MsaaTarget<u32>, size| {
+            target.fit(size, |_| {
+                created += 1;
+                created
+            })
+        };
+
+        fit(&mut target, (800, 600));
+        fit(&mut target, (800, 600));
+        assert_eq!(target.view(), Some(&1));
+
+        fit(&mut target, (1024, 600));
+        assert_eq!(target.view(), Some(&2));
+        fit(&mut target, (1024, 600));
+        assert_eq!(target.view(), Some(&2));
+    }
+
+    #[test]
+    fn clearing_is_fine_with_msaa() {
+        assert_loadable(LoadOp::Clear(Color::TRANSPARENT), 4);
+        assert_loadable(LoadOp::Load, 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "sample_count of 1")]
+    fn loading_is_rejected_with_msaa() {
+        assert_loadable(LoadOp::Load, 4);
+    }
+}
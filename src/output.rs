@@ -0,0 +1,64 @@
+/// What egui and the [`UiState`](crate::UiState) ask of the host after
+/// a frame.
+#[derive(Clone)]
+pub struct FrameOutput<A> {
+    /// What the UI state returned from `draw`.
+    pub action: Option<A>,
+    /// The cursor the OS should show.
+    pub cursor_icon: egui::CursorIcon,
+    /// A hyperlink that was clicked.
+    pub open_url: Option<String>,
+    /// Text to put on the clipboard.
+    pub copied_text: Option<String>,
+    /// egui wants another frame even without new input, e.g. while
+    /// animating.
+    pub needs_repaint: bool,
+}
+
+impl<A> FrameOutput<A> {
+    pub(crate) fn new(output: egui::Output, action: Option<A>) -> Self {
+        let egui::Output {
+            cursor_icon,
+            open_url,
+            copied_text,
+            needs_repaint,
+        } = output;
+        Self {
+            action,
+            cursor_icon,
+            open_url,
+            copied_text: Some(copied_text).filter(|text| !text.is_empty()),
+            needs_repaint,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::FrameOutput;
+
+    fn egui_output(copied_text: &str) -> egui::Output {
+        egui::Output {
+            cursor_icon: egui::CursorIcon::Text,
+            open_url: Some("https://example.com".into()),
+            copied_text: copied_text.into(),
+            needs_repaint: true,
+        }
+    }
+
+    #[test]
+    fn egui_output_is_carried_over() {
+        let output = FrameOutput::new(egui_output("hello"), Some(3));
+        assert_eq!(output.action, Some(3));
+        assert!(matches!(output.cursor_icon, egui::CursorIcon::Text));
+        assert_eq!(output.open_url.as_deref(), Some("https://example.com"));
+        assert_eq!(output.copied_text.as_deref(), Some("hello"));
+        assert!(output.needs_repaint);
+    }
+
+    #[test]
+    fn nothing_copied_is_none() {
+        let output = FrameOutput::<()>::new(egui_output(""), None);
+        assert_eq!(output.copied_text, None);
+    }
+}
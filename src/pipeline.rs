@@ -1,15 +1,28 @@
 use std::{mem::size_of, num::NonZeroU64};
 use wgpu::*;
 
-use crate::{default_mod, load_frag, load_vert};
+use crate::{default_mod, load_frag, load_vert, HdrSettings, OutputColorSpace};
+
+/// What the pipeline is built for, see `EguiRendererDescriptor`.
+pub struct PipelineDescriptor {
+    pub fmt: TextureFormat,
+    pub space: OutputColorSpace,
+    pub hdr: Option<HdrSettings>,
+    pub sample_count: u32,
+    pub screen_dims: (f32, f32),
+}
 
 pub struct Pipeline {
     pub pl: RenderPipeline,
     pub vert_bg: BindGroup,
     pub frag_bg: BindGroup,
+    pub frag_layout: BindGroupLayout,
+    pub sampler: Sampler,
     pub egui_tex: Texture,
     pub vert_uniform_buf: Buffer,
     pub tex_hash: u64,
+    tex_dims: (usize, usize),
+    screen_dims: (f32, f32),
 }
 
 impl Pipeline {
@@ -17,28 +30,35 @@ impl Pipeline {
         dev: &Device,
         q: &Queue,
         tex: &egui::paint::Texture,
-        fmt: TextureFormat,
-        screen_dims: (f32, f32),
+        desc: PipelineDescriptor,
     ) -> Self {
+        let PipelineDescriptor {
+            fmt,
+            space,
+            hdr,
+            sample_count,
+            screen_dims,
+        } = desc;
+
         // TODO: put these in const position with an updated version of the
         // layout macro
         let vert_layout = dev.create_bind_group_layout(&BindGroupLayoutDescriptor {
             label: Some("egui-wgpu :: vert_bind_group_layout"),
             entries: &[BindGroupLayoutEntry {
-                visibility: ShaderStage::VERTEX,
+                visibility: ShaderStage::VERTEX | ShaderStage::FRAGMENT,
                 binding: 0,
                 count: None,
                 ty: BindingType::UniformBuffer {
                     dynamic: false,
-                    min_binding_size: NonZeroU64::new(size_of::<[f32; 4]>() as u64),
+                    min_binding_size: NonZeroU64::new(size_of::<[f32; 6]>() as u64),
                 },
             }],
         });
 
         let vert_uniform_buf = dev.create_buffer(&BufferDescriptor {
             label: Some("egui-wgpu :: vertex_uniform_buffer"),
-            size: size_of::<[f32; 4]>() as u64,
-            usage: BufferUsage::UNIFORM,
+            size: size_of::<[f32; 6]>() as u64,
+            usage: BufferUsage::UNIFORM | BufferUsage::COPY_DST,
             mapped_at_creation: true,
         });
 
@@ -50,6 +70,8 @@ impl Pipeline {
                 screen_dims.1,
                 tex.width as f32,
                 tex.height as f32,
+                hdr.map_or(1., |hdr| hdr.brightness()),
+                hdr.map_or(1., |hdr| hdr.alpha_power()),
             ]));
         vert_uniform_buf.unmap();
 
@@ -84,43 +106,8 @@ impl Pipeline {
             ],
         });
 
-        let egui_tex = dev.create_texture(&TextureDescriptor {
-            label: Some("egui-wgpu :: main_texture"),
-            size: wgpu::Extent3d {
-                height: tex.height as u32,
-                width: tex.width as u32,
-                depth: 1,
-            },
-            mip_level_count: 1,
-            sample_count: 1,
-            dimension: TextureDimension::D2,
-            format: TextureFormat::Rgba8UnormSrgb,
-            usage: TextureUsage::SAMPLED | TextureUsage::COPY_DST,
-        });
-
-        let pixels = tex.pixels.iter().fold(Vec::<u8>::new(), |mut acc, b| {
-            acc.extend(&[*b; 4]);
-            acc
-        });
-
-        q.write_texture(
-            wgpu::TextureCopyView {
-                texture: &egui_tex,
-                mip_level: 0,
-                origin: wgpu::Origin3d::ZERO,
-            },
-            &pixels,
-            wgpu::TextureDataLayout {
-                offset: 0,
-                bytes_per_row: tex.width as u32 * 4,
-                rows_per_image: tex.height as u32,
-            },
-            wgpu::Extent3d {
-                width: tex.width as u32,
-                height: tex.height as u32,
-                depth: 1,
-            },
-        );
+        let egui_tex = create_atlas(dev, tex);
+        upload_atlas(q, &egui_tex, tex);
 
         let egui_sampler = dev.create_sampler(&SamplerDescriptor {
             label: Some("egui-wgpu :: main_sampler"),
@@ -128,27 +115,7 @@ impl Pipeline {
             ..Default::default()
         });
 
-        let frag_bg = dev.create_bind_group(&BindGroupDescriptor {
-            label: Some("egui-wgpu :: frag_bind_group"),
-            layout: &frag_layout,
-            entries: &[
-                BindGroupEntry {
-                    binding: 0,
-                    resource: BindingResource::Sampler(&egui_sampler),
-                },
-                BindGroupEntry {
-                    binding: 1,
-                    resource: BindingResource::TextureView(&egui_tex.create_view(
-                        &TextureViewDescriptor {
-                            label: Some("egui-wgpu :: main_texture_view"),
-                            format: Some(TextureFormat::Rgba8UnormSrgb),
-                            dimension: Some(TextureViewDimension::D2),
-                            ..Default::default()
-                        },
-                    )),
-                },
-            ],
-        });
+        let frag_bg = create_frag_bg(dev, &frag_layout, &atlas_view(&egui_tex), &egui_sampler);
 
         let pl_layout = dev.create_pipeline_layout(&PipelineLayoutDescriptor {
             label: Some("egui-wgpu :: render_pl_layout"),
@@ -187,14 +154,14 @@ impl Pipeline {
         let pl = dev.create_render_pipeline(&RenderPipelineDescriptor {
             label: Some("egui-wgpu :: render_pl"),
             layout: Some(&pl_layout),
-            vertex_stage: default_mod(&load_vert(&dev)),
-            fragment_stage: Some(default_mod(&load_frag(&dev))),
+            vertex_stage: default_mod(&load_vert(dev, space)),
+            fragment_stage: Some(default_mod(&load_frag(dev, space))),
             rasterization_state: None,
             primitive_topology: PrimitiveTopology::TriangleList,
             color_states: &[color_state],
             depth_stencil_state: None,
             vertex_state,
-            sample_count: 1,
+            sample_count,
             sample_mask: !0,
             alpha_to_coverage_enabled: false,
         });
@@ -202,35 +169,160 @@ impl Pipeline {
         Self {
             pl,
             frag_bg,
+            frag_layout,
+            sampler: egui_sampler,
             vert_bg,
             vert_uniform_buf,
             egui_tex,
             tex_hash: tex.id,
+            tex_dims: (tex.width, tex.height),
+            screen_dims,
         }
     }
 
-    pub fn resize(&mut self, w: f32, h: f32) {}
+    /// Creates a fragment bind group for any texture view, falling back to
+    /// the font atlas sampler when `sampler` is `None`.
+    pub fn texture_bg(
+        &self,
+        dev: &Device,
+        view: &TextureView,
+        sampler: Option<&Sampler>,
+    ) -> BindGroup {
+        create_frag_bg(
+            dev,
+            &self.frag_layout,
+            view,
+            sampler.unwrap_or(&self.sampler),
+        )
+    }
 
-    pub fn rebuild_texture(&mut self, queue: &Queue, ctx: &egui::Context) {
+    /// Updates the `u_screen_size` uniform, `w` and `h` are in points.
+    pub fn resize(&mut self, queue: &Queue, w: f32, h: f32) {
+        if self.screen_dims == (w, h) {
+            return;
+        }
+        self.screen_dims = (w, h);
+        queue.write_buffer(&self.vert_uniform_buf, 0, bytemuck::cast_slice(&[w, h]));
+    }
+
+    /// Re-uploads the font atlas, recreating the texture and its bind group
+    /// when the atlas dimensions changed.
+    pub fn rebuild_texture(&mut self, dev: &Device, queue: &Queue, ctx: &egui::Context) {
         let tex = ctx.texture();
         self.tex_hash = tex.id;
-        queue.write_texture(
-            wgpu::TextureCopyView {
-                texture: &self.egui_tex,
-                mip_level: 0,
-                origin: wgpu::Origin3d::ZERO,
-            },
-            &tex.pixels,
-            wgpu::TextureDataLayout {
-                offset: 0,
-                bytes_per_row: tex.width as u32,
-                rows_per_image: tex.height as u32,
+
+        if self.tex_dims != (tex.width, tex.height) {
+            self.tex_dims = (tex.width, tex.height);
+            self.egui_tex = create_atlas(dev, tex);
+            self.frag_bg = create_frag_bg(
+                dev,
+                &self.frag_layout,
+                &atlas_view(&self.egui_tex),
+                &self.sampler,
+            );
+            queue.write_buffer(
+                &self.vert_uniform_buf,
+                size_of::<[f32; 2]>() as u64,
+                bytemuck::cast_slice(&[tex.width as f32, tex.height as f32]),
+            );
+        }
+
+        upload_atlas(queue, &self.egui_tex, tex);
+    }
+}
+
+fn create_atlas(dev: &Device, tex: &egui::paint::Texture) -> Texture {
+    dev.create_texture(&TextureDescriptor {
+        label: Some("egui-wgpu :: main_texture"),
+        size: wgpu::Extent3d {
+            height: tex.height as u32,
+            width: tex.width as u32,
+            depth: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: TextureDimension::D2,
+        format: TextureFormat::Rgba8UnormSrgb,
+        usage: TextureUsage::SAMPLED | TextureUsage::COPY_DST,
+    })
+}
+
+fn atlas_view(egui_tex: &Texture) -> TextureView {
+    egui_tex.create_view(&TextureViewDescriptor {
+        label: Some("egui-wgpu :: main_texture_view"),
+        format: Some(TextureFormat::Rgba8UnormSrgb),
+        dimension: Some(TextureViewDimension::D2),
+        ..Default::default()
+    })
+}
+
+fn upload_atlas(q: &Queue, egui_tex: &Texture, tex: &egui::paint::Texture) {
+    q.write_texture(
+        wgpu::TextureCopyView {
+            texture: egui_tex,
+            mip_level: 0,
+            origin: wgpu::Origin3d::ZERO,
+        },
+        &expand_alpha(&tex.pixels),
+        wgpu::TextureDataLayout {
+            offset: 0,
+            bytes_per_row: tex.width as u32 * 4,
+            rows_per_image: tex.height as u32,
+        },
+        wgpu::Extent3d {
+            width: tex.width as u32,
+            height: tex.height as u32,
+            depth: 1,
+        },
+    );
+}
+
+/// The atlas egui hands us is coverage only, every channel of the
+/// uploaded texel gets the same value.
+fn expand_alpha(pixels: &[u8]) -> Vec<u8> {
+    pixels.iter().flat_map(|&b| [b; 4]).collect()
+}
+
+fn create_frag_bg(
+    dev: &Device,
+    layout: &BindGroupLayout,
+    view: &TextureView,
+    sampler: &Sampler,
+) -> BindGroup {
+    dev.create_bind_group(&BindGroupDescriptor {
+        label: Some("egui-wgpu :: frag_bind_group"),
+        layout,
+        entries: &[
+            BindGroupEntry {
+                binding: 0,
+                resource: BindingResource::Sampler(sampler),
             },
-            wgpu::Extent3d {
-                width: tex.width as u32,
-                height: tex.height as u32,
-                depth: 1,
+            BindGroupEntry {
+                binding: 1,
+                resource: BindingResource::TextureView(view),
             },
+        ],
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::expand_alpha;
+
+    #[test]
+    fn alpha_is_expanded_to_rgba() {
+        assert_eq!(
+            expand_alpha(&[0, 128, 255]),
+            vec![0, 0, 0, 0, 128, 128, 128, 128, 255, 255, 255, 255]
         );
     }
+
+    #[test]
+    fn expanded_atlas_matches_texture_size() {
+        let (w, h) = (13, 7);
+        let pixels = vec![42; w * h];
+        let rgba = expand_alpha(&pixels);
+        assert_eq!(rgba.len(), w * h * 4);
+        assert!(rgba.chunks(4).all(|px| px == [42, 42, 42, 42]));
+    }
 }
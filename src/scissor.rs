@@ -0,0 +1,57 @@
+/// Converts a clip rect in points, as `(min_x, min_y, max_x, max_y)`, into
+/// a scissor rect in physical pixels as `(x, y, width, height)`. Both
+/// corners are scaled and rounded to the nearest pixel edge and clamped to
+/// the `target` size; an empty result is `None`, since wgpu rejects
+/// zero-sized scissors.
+pub fn scissor_rect(
+    clip: (f32, f32, f32, f32),
+    ppp: f32,
+    target: (u32, u32),
+) -> Option<(u32, u32, u32, u32)> {
+    let (x0, y0, x1, y1) = clip;
+    let (w, h) = target;
+    let edge = |v: f32, max: u32| (v * ppp).round().max(0.).min(max as f32) as u32;
+
+    let (min_x, max_x) = (edge(x0, w), edge(x1, w));
+    let (min_y, max_y) = (edge(y0, h), edge(y1, h));
+    if min_x >= max_x || min_y >= max_y {
+        return None;
+    }
+    Some((min_x, min_y, max_x - min_x, max_y - min_y))
+}
+
+#[cfg(test)]
+mod test {
+    use super::scissor_rect;
+
+    #[test]
+    fn both_corners_are_scaled() {
+        assert_eq!(
+            scissor_rect((10., 20., 30., 40.), 2., (100, 100)),
+            Some((20, 40, 40, 40))
+        );
+    }
+
+    #[test]
+    fn fractional_edges_round_to_nearest() {
+        assert_eq!(
+            scissor_rect((0.2, 0.6, 10.4, 10.5), 1.5, (100, 100)),
+            Some((0, 1, 16, 15))
+        );
+    }
+
+    #[test]
+    fn rects_are_clamped_to_the_target() {
+        assert_eq!(
+            scissor_rect((-10., -10., 1000., 1000.), 1., (64, 32)),
+            Some((0, 0, 64, 32))
+        );
+    }
+
+    #[test]
+    fn empty_and_offscreen_rects_are_skipped() {
+        assert_eq!(scissor_rect((5., 5., 5., 10.), 1., (64, 64)), None);
+        assert_eq!(scissor_rect((70., 0., 80., 10.), 1., (64, 64)), None);
+        assert_eq!(scissor_rect((10., 10., 0., 0.), 1., (64, 64)), None);
+    }
+}
@@ -0,0 +1,113 @@
+use egui::{vec2, Vec2};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScrollSettings {
+    /// How many points a single `ScrollLines` step moves.
+    pub points_per_line: f32,
+    /// Spreads scrolling over several frames, each frame moves this
+    /// fraction of the distance still pending, which must lie in
+    /// `(0, 1]`. `None` applies every delta in the frame it arrived.
+    pub smoothing: Option<f32>,
+}
+
+impl Default for ScrollSettings {
+    fn default() -> Self {
+        Self {
+            points_per_line: 50.,
+            smoothing: None,
+        }
+    }
+}
+
+/// Scroll distance received but not yet handed to egui.
+#[derive(Debug, Default)]
+pub struct PendingScroll {
+    pending: Vec2,
+}
+
+impl ScrollSettings {
+    /// Panics if `smoothing` is outside `(0, 1]`, a zero would never
+    /// scroll and anything above one overshoots.
+    pub fn validate(&self) {
+        if let Some(f) = self.smoothing {
+            assert!(
+                f > 0. && f <= 1.,
+                "egui-wgpu :: scroll smoothing must be in (0, 1], got {}",
+                f
+            );
+        }
+    }
+}
+
+impl PendingScroll {
+    pub fn push(&mut self, delta: Vec2) {
+        self.pending += delta;
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pending == vec2(0., 0.)
+    }
+
+    /// The delta for the next frame.
+    pub fn take(&mut self, settings: &ScrollSettings) -> Vec2 {
+        let step = match settings.smoothing {
+            Some(f) if (self.pending * f).length() >= 0.5 => self.pending * f,
+            _ => self.pending,
+        };
+        self.pending -= step;
+        if self.pending.length() < 1e-3 {
+            self.pending = vec2(0., 0.);
+        }
+        step
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn deltas_accumulate_within_a_frame() {
+        let mut scroll = PendingScroll::default();
+        scroll.push(vec2(0., 10.));
+        scroll.push(vec2(0., 5.));
+        assert_eq!(scroll.take(&ScrollSettings::default()), vec2(0., 15.));
+        assert_eq!(scroll.pending, vec2(0., 0.));
+    }
+
+    #[test]
+    fn smoothing_spreads_over_frames() {
+        let settings = ScrollSettings {
+            smoothing: Some(0.5),
+            ..Default::default()
+        };
+        let mut scroll = PendingScroll::default();
+        scroll.push(vec2(0., 8.));
+        assert_eq!(scroll.take(&settings), vec2(0., 4.));
+        assert_eq!(scroll.take(&settings), vec2(0., 2.));
+
+        let rest: f32 = (0..10).map(|_| scroll.take(&settings).y).sum();
+        assert_eq!(rest, 2.);
+        assert_eq!(scroll.pending, vec2(0., 0.));
+    }
+
+    #[test]
+    #[should_panic]
+    fn zero_smoothing_is_rejected() {
+        let settings = ScrollSettings {
+            smoothing: Some(0.),
+            ..Default::default()
+        };
+        settings.validate();
+    }
+
+    #[test]
+    #[should_panic]
+    fn smoothing_above_one_is_rejected() {
+        let settings = ScrollSettings {
+            smoothing: Some(1.5),
+            ..Default::default()
+        };
+        settings.validate();
+    }
+}
@@ -1,3 +1,4 @@
+use crate::OutputColorSpace;
 use wgpu::{
     include_spirv, // BindGroupLayoutDescriptor,
     Device,
@@ -13,19 +14,27 @@ pub fn default_mod<'a>(module: &'a ShaderModule) -> ProgrammableStageDescriptor<
     }
 }
 
-pub fn load_vert(dev: &Device) -> ShaderModule {
-    let src = include_spirv!("vert.spv");
+pub fn load_vert(dev: &Device, space: OutputColorSpace) -> ShaderModule {
+    let src = match space {
+        OutputColorSpace::Linear => include_spirv!("vert.spv"),
+        OutputColorSpace::Gamma => include_spirv!("vert_gamma.spv"),
+    };
     dev.create_shader_module(src)
 }
 
-pub fn load_frag(dev: &Device) -> ShaderModule {
-    let src = include_spirv!("frag.spv");
+pub fn load_frag(dev: &Device, space: OutputColorSpace) -> ShaderModule {
+    let src = match space {
+        OutputColorSpace::Linear => include_spirv!("frag.spv"),
+        OutputColorSpace::Gamma => include_spirv!("frag_gamma.spv"),
+    };
     dev.create_shader_module(src)
 }
 
 #[cfg(test)]
 mod test {
     use super::{load_frag, load_vert};
+    use crate::{EguiRenderer, EguiRendererDescriptor, OutputColorSpace::*, UiState};
+    use egui::{paint::color::WHITE, pos2, Rect};
     use futures::executor::block_on;
 
     #[test]
@@ -38,7 +47,8 @@ mod test {
         .unwrap();
         let (device, _) =
             block_on(adapter.request_device(&wgpu::DeviceDescriptor::default(), None)).unwrap();
-        let _ = load_vert(&device);
+        let _ = load_vert(&device, Linear);
+        let _ = load_vert(&device, Gamma);
     }
 
     #[test]
@@ -51,6 +61,51 @@ mod test {
         .unwrap();
         let (device, _) =
             block_on(adapter.request_device(&wgpu::DeviceDescriptor::default(), None)).unwrap();
-        let _ = load_frag(&device);
+        let _ = load_frag(&device, Linear);
+        let _ = load_frag(&device, Gamma);
+    }
+
+    /// Fills the top left 8x8 points.
+    struct Corner;
+
+    impl UiState for Corner {
+        type Action = ();
+
+        fn draw(&mut self, ui: &mut egui::Ui) -> Option<()> {
+            let rect = Rect::from_min_max(pos2(0., 0.), pos2(8., 8.));
+            ui.painter().rect_filled(rect, 0., WHITE);
+            None
+        }
+    }
+
+    #[test]
+    fn gamma_vert_puts_the_origin_top_left() {
+        let instance = wgpu::Instance::new(wgpu::BackendBit::PRIMARY);
+        let adapter = block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
+            power_preference: wgpu::PowerPreference::default(),
+            compatible_surface: None,
+        }))
+        .unwrap();
+        let (device, queue) =
+            block_on(adapter.request_device(&wgpu::DeviceDescriptor::default(), None)).unwrap();
+        let mut renderer = EguiRenderer::new(
+            &device,
+            &queue,
+            EguiRendererDescriptor {
+                state: Corner,
+                // a Unorm target picks the Gamma variant
+                fmt: wgpu::TextureFormat::Rgba8Unorm,
+                screen_size: (32., 32.),
+                ppp: 1.,
+                load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                color_space: None,
+                hdr: None,
+                sample_count: 1,
+            },
+        );
+        let (image, _) = renderer.capture(&device, &queue, (32, 32));
+        let pixel = |x: usize, y: usize| &image.pixels[(y * 32 + x) * 4..][..4];
+        assert_eq!(pixel(2, 2), &[255; 4]);
+        assert_eq!(pixel(2, 29), &[0, 0, 0, 255]);
     }
 }
@@ -1,5 +1,12 @@
 #version 450
 
+layout (set = 0, binding = 0) uniform Globals {
+  vec2 u_screen_size;
+  vec2 u_tex_size;
+  float u_brightness;
+  float u_alpha_power;
+};
+
 layout(set = 1, binding = 0) uniform sampler u_sampler;
 layout(set = 1, binding = 1) uniform texture2D u_tex;
 
@@ -11,5 +18,8 @@ layout(location = 0) out vec4 o_color;
 void main() {
   o_color = v_color;
   o_color *= texture(sampler2D(u_tex, u_sampler), v_tc);
+  o_color.rgb *= u_brightness;
+  // only the coverage used for blending changes, the color stays
+  // premultiplied by the original alpha
+  o_color.a = 1.0 - pow(1.0 - o_color.a, u_alpha_power);
 }
-
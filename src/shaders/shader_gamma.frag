@@ -0,0 +1,23 @@
+#version 450
+
+layout(set = 1, binding = 0) uniform sampler u_sampler;
+layout(set = 1, binding = 1) uniform texture2D u_tex;
+
+layout(location = 0) in vec2 v_tc;
+layout(location = 1) in vec4 v_color;
+
+layout(location = 0) out vec4 o_color;
+
+vec3 srgb_from_linear(vec3 rgb) {
+  bvec3 cutoff = lessThan(rgb, vec3(0.0031308));
+  vec3 lower = rgb * vec3(12.92);
+  vec3 higher = vec3(1.055) * pow(rgb, vec3(1.0 / 2.4)) - vec3(0.055);
+  return mix(higher, lower, cutoff);
+}
+
+// Textures are sampled from sRGB views and come back linear, encode them
+// again to match the vertex color.
+void main() {
+  vec4 texel = texture(sampler2D(u_tex, u_sampler), v_tc);
+  o_color = v_color * vec4(srgb_from_linear(texel.rgb), texel.a);
+}
@@ -0,0 +1,315 @@
+//! A pure Rust reference rasterizer for egui paint jobs.
+//!
+//! It mirrors `shader.vert` and `shader.frag` rendering into an sRGB
+//! target with the blend state set up in `Pipeline::new`, so its output
+//! can be used where no adapter exists and to validate the gpu path.
+
+use crate::{mesh::Mesh, scissor::scissor_rect, EguiRenderer, RgbaImage, TextureId, UiState, V};
+use egui::paint;
+
+/// The font atlas as the fragment shader sees it.
+struct Atlas<'a> {
+    width: usize,
+    height: usize,
+    coverage: &'a [u8],
+}
+
+/// Rasterizes `jobs` into a `size` physical pixel image cleared to black,
+/// as `draw_on` does with `LoadOp::Clear(Color::BLACK)`.
+pub fn rasterize(
+    jobs: &[(egui::Rect, paint::Triangles)],
+    tex: &paint::Texture,
+    size: (u32, u32),
+    ppp: f32,
+) -> RgbaImage {
+    let mesh = Mesh::build(jobs.to_vec(), &[], (tex.width, tex.height));
+    rasterize_mesh(&mesh, tex, size, ppp)
+}
+
+/// Rasterizes the draw calls of `mesh` that sample the font atlas, user
+/// textures only exist on the gpu.
+pub(crate) fn rasterize_mesh(
+    mesh: &Mesh,
+    tex: &paint::Texture,
+    size: (u32, u32),
+    ppp: f32,
+) -> RgbaImage {
+    let (width, height) = (size.0 as usize, size.1 as usize);
+    let atlas = Atlas {
+        width: tex.width,
+        height: tex.height,
+        coverage: &tex.pixels,
+    };
+
+    // linear, premultiplied colors as they would sit in the render target
+    let mut target = vec![[0., 0., 0., 1.]; width * height];
+    let calls = mesh
+        .calls
+        .iter()
+        .filter(|call| call.texture == TextureId::Egui);
+    calls.for_each(|call| {
+        draw_job(
+            &mut target,
+            width,
+            height,
+            ppp,
+            call.clip,
+            &mesh.verts[call.vert_range()],
+            &mesh.idxs[call.idx_range()],
+            &atlas,
+        );
+    });
+
+    RgbaImage {
+        width: size.0,
+        height: size.1,
+        pixels: target.iter().flat_map(|px| encode(*px).to_vec()).collect(),
+    }
+}
+
+impl<S> EguiRenderer<S>
+where
+    S: UiState,
+{
+    /// Rasterizes the frame built by the last [`EguiRenderer::prepare`]
+    /// on the cpu, e.g. to check [`EguiRenderer::capture`] against it.
+    /// User images are left out.
+    pub fn rasterize(&self, size: (u32, u32)) -> RgbaImage {
+        rasterize_mesh(&self.mesh, self.ui.ctx.texture(), size, self.ui.ppp())
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn draw_job(
+    target: &mut [[f32; 4]],
+    width: usize,
+    height: usize,
+    ppp: f32,
+    clip: (f32, f32, f32, f32),
+    verts: &[V],
+    idxs: &[u32],
+    atlas: &Atlas,
+) {
+    let (sx, sy, sw, sh) = match scissor_rect(clip, ppp, (width as u32, height as u32)) {
+        Some(rect) => rect,
+        None => return,
+    };
+    let x_range = (sx as usize, (sx + sw) as usize);
+    let y_range = (sy as usize, (sy + sh) as usize);
+
+    idxs.chunks_exact(3).for_each(|tri| {
+        let [a, b, c] = [
+            &verts[tri[0] as usize],
+            &verts[tri[1] as usize],
+            &verts[tri[2] as usize],
+        ];
+        let p = |v: &V| (v.a_pos[0] * ppp, v.a_pos[1] * ppp);
+        let (pa, pb, pc) = (p(a), p(b), p(c));
+        let area = edge(pa, pb, pc);
+        if area == 0. {
+            return;
+        }
+
+        let min_x = pa.0.min(pb.0).min(pc.0).floor().max(x_range.0 as f32) as usize;
+        let max_x = pa.0.max(pb.0).max(pc.0).ceil().min(x_range.1 as f32) as usize;
+        let min_y = pa.1.min(pb.1).min(pc.1).floor().max(y_range.0 as f32) as usize;
+        let max_y = pa.1.max(pb.1).max(pc.1).ceil().min(y_range.1 as f32) as usize;
+
+        for y in min_y..max_y {
+            for x in min_x..max_x {
+                let s = (x as f32 + 0.5, y as f32 + 0.5);
+                let (wa, wb, wc) = (edge(pb, pc, s), edge(pc, pa, s), edge(pa, pb, s));
+                let inside = if area > 0. {
+                    wa >= 0. && wb >= 0. && wc >= 0.
+                } else {
+                    wa <= 0. && wb <= 0. && wc <= 0.
+                };
+                if !inside {
+                    continue;
+                }
+                let (wa, wb, wc) = (wa / area, wb / area, wc / area);
+                let lerp = |f: &dyn Fn(&V) -> f32| wa * f(a) + wb * f(b) + wc * f(c);
+
+                let tc = (lerp(&|v| v.a_tc[0]), lerp(&|v| v.a_tc[1]));
+                let mut src = [0.; 4];
+                for (i, ch) in src.iter_mut().enumerate() {
+                    *ch = lerp(&|v| vertex_color(v.a_srgba)[i]);
+                }
+                let texel = atlas.sample(tc);
+                for (ch, t) in src.iter_mut().zip(&texel) {
+                    *ch *= t;
+                }
+
+                let dst = &mut target[y * width + x];
+                *dst = blend(src, *dst);
+            }
+        }
+    });
+}
+
+fn edge(a: (f32, f32), b: (f32, f32), p: (f32, f32)) -> f32 {
+    (b.0 - a.0) * (p.1 - a.1) - (b.1 - a.1) * (p.0 - a.0)
+}
+
+impl Atlas<'_> {
+    /// Nearest sampling of the `Rgba8UnormSrgb` atlas, every texel holds
+    /// the coverage in all four channels. `tc` is in texels, the shader
+    /// divides by `u_tex_size` only to hand the sampler normalized coords.
+    fn sample(&self, tc: (f32, f32)) -> [f32; 4] {
+        let x = (tc.0.max(0.) as usize).min(self.width - 1);
+        let y = (tc.1.max(0.) as usize).min(self.height - 1);
+        let c = self.coverage[y * self.width + x];
+        let rgb = linear_from_srgb(c as f32);
+        [rgb, rgb, rgb, c as f32 / 255.]
+    }
+}
+
+/// `linear_from_srgba` in `shader.vert`, `srgba` holds 0-255 values.
+fn vertex_color(srgba: [u8; 4]) -> [f32; 4] {
+    let [r, g, b, a] = srgba;
+    [
+        linear_from_srgb(r as f32),
+        linear_from_srgb(g as f32),
+        linear_from_srgb(b as f32),
+        a as f32 / 255.,
+    ]
+}
+
+/// `linear_from_srgb` in `shader.vert`, takes a 0-255 value.
+fn linear_from_srgb(srgb: f32) -> f32 {
+    if srgb < 10.31475 {
+        srgb / 3294.6
+    } else {
+        ((srgb + 14.025) / 269.025).powf(2.4)
+    }
+}
+
+fn srgb_from_linear(linear: f32) -> f32 {
+    if linear <= 0.003_130_8 {
+        linear * 12.92
+    } else {
+        1.055 * linear.powf(1. / 2.4) - 0.055
+    }
+}
+
+/// The blend state from `Pipeline::new`.
+fn blend(src: [f32; 4], dst: [f32; 4]) -> [f32; 4] {
+    let color = |i: usize| src[i] + dst[i] * (1. - src[3]);
+    [
+        color(0),
+        color(1),
+        color(2),
+        src[3] * (1. - dst[3]) + dst[3],
+    ]
+}
+
+/// Stores a linear color into an `*Srgb` target.
+fn encode(px: [f32; 4]) -> [u8; 4] {
+    let q = |v: f32| (v.clamp(0., 1.) * 255.).round() as u8;
+    [
+        q(srgb_from_linear(px[0])),
+        q(srgb_from_linear(px[1])),
+        q(srgb_from_linear(px[2])),
+        q(px[3]),
+    ]
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn vert(x: f32, y: f32, color: [u8; 4]) -> V {
+        V {
+            a_pos: [x, y],
+            a_tc: [0., 0.],
+            a_srgba: color,
+        }
+    }
+
+    const OPAQUE: Atlas = Atlas {
+        width: 1,
+        height: 1,
+        coverage: &[255],
+    };
+
+    #[test]
+    fn srgb_round_trips() {
+        for v in [0u8, 1, 10, 11, 128, 254, 255].iter() {
+            let back = srgb_from_linear(linear_from_srgb(*v as f32)) * 255.;
+            assert!((back - *v as f32).abs() < 0.5, "{} -> {}", v, back);
+        }
+    }
+
+    #[test]
+    fn triangle_is_filled_and_clipped() {
+        let (w, h) = (4, 4);
+        let mut target = vec![[0., 0., 0., 1.]; w * h];
+        let verts = [
+            vert(0., 0., [255, 255, 255, 255]),
+            vert(8., 0., [255, 255, 255, 255]),
+            vert(0., 8., [255, 255, 255, 255]),
+        ];
+        draw_job(
+            &mut target,
+            w,
+            h,
+            1.,
+            (0., 0., 2., 4.),
+            &verts,
+            &[0, 1, 2],
+            &OPAQUE,
+        );
+
+        let lit: Vec<_> = target.iter().map(|px| px[0] > 0.5).collect();
+        assert!(lit[0] && lit[1] && lit[w]);
+        assert!(
+            !lit[2] && !lit[3],
+            "pixels right of the clip rect were drawn"
+        );
+    }
+
+    #[test]
+    fn premultiplied_blend_over_black() {
+        let out = blend([0.5, 0., 0., 0.5], [0., 0., 1., 1.]);
+        assert_eq!(out, [0.5, 0., 0.5, 1.]);
+    }
+
+    #[test]
+    fn ppp_scales_geometry() {
+        let (w, h) = (4, 4);
+        let mut target = vec![[0., 0., 0., 1.]; w * h];
+        let verts = [
+            vert(0., 0., [255, 0, 0, 255]),
+            vert(2., 0., [255, 0, 0, 255]),
+            vert(2., 2., [255, 0, 0, 255]),
+            vert(0., 2., [255, 0, 0, 255]),
+        ];
+        draw_job(
+            &mut target,
+            w,
+            h,
+            2.,
+            (0., 0., 2., 2.),
+            &verts,
+            &[0, 1, 2, 0, 2, 3],
+            &OPAQUE,
+        );
+        assert!(target.iter().all(|px| px[0] > 0.99));
+    }
+
+    #[test]
+    fn user_textures_are_left_out() {
+        let tex = paint::Texture {
+            id: 0,
+            width: 1,
+            height: 1,
+            pixels: vec![255],
+        };
+        let everything = egui::Rect::from_min_max(egui::pos2(0., 0.), egui::pos2(4., 4.));
+        let image = crate::UserImage::new(TextureId::User(0), everything, everything);
+        let mesh = Mesh::build(vec![], &[image], (1, 1));
+
+        let image = rasterize_mesh(&mesh, &tex, (4, 4), 1.);
+        assert!(image.pixels.chunks(4).all(|px| px == [0, 0, 0, 255]));
+    }
+}
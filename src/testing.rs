@@ -0,0 +1,359 @@
+//! Golden image snapshot tests for [`UiState`] implementations.
+//!
+//! Frames are rendered headlessly and compared against PNGs on disk. Set
+//! `EGUI_WGPU_UPDATE_GOLDEN` to write the golden images instead of
+//! comparing against them.
+
+use crate::{
+    frontend::Frontend, mesh::Mesh, software::rasterize_mesh, EguiRenderer, EguiRendererDescriptor,
+    EventBridge, ManualClock, RgbaImage, UiState,
+};
+use futures::executor::block_on;
+use std::{fmt, fs::File, io, path::Path, path::PathBuf};
+use wgpu::*;
+
+const FMT: TextureFormat = TextureFormat::Rgba8UnormSrgb;
+
+/// What renders the snapshot. The two differ in atlas filtering and
+/// anti-aliasing, so a golden image only matches the backend that wrote
+/// it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backend {
+    /// Whatever [`headless_device`] finds, pixels can still differ a
+    /// little between adapters.
+    Gpu,
+    /// The [`software`](crate::software) rasterizer, the same on every
+    /// machine and what goldens checked into a repository should use.
+    /// User images are left out.
+    Software,
+}
+
+pub struct SnapshotDescriptor<S: UiState> {
+    pub state: S,
+    /// Size of the rendered image in physical pixels.
+    pub size: (u32, u32),
+    pub ppp: f32,
+    /// Events to feed the UI, one entry per frame. The image is taken
+    /// after the last frame, no frames is the same as one without events.
+    pub frames: Vec<Vec<EventBridge>>,
+    pub backend: Backend,
+    /// Largest per channel difference still considered equal.
+    pub tolerance: u8,
+}
+
+#[derive(Debug)]
+pub enum SnapshotError {
+    /// [`Backend::Gpu`] was asked for but no adapter could be found.
+    NoAdapter,
+    /// There is no golden image to compare against.
+    MissingGolden(PathBuf),
+    SizeMismatch {
+        expected: (u32, u32),
+        actual: (u32, u32),
+    },
+    PixelsDiffer {
+        count: usize,
+        diff: PathBuf,
+    },
+    Io(io::Error),
+    Encode(png::EncodingError),
+    Decode(png::DecodingError),
+}
+
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SnapshotError::NoAdapter => write!(f, "no adapter available for rendering"),
+            SnapshotError::MissingGolden(path) => write!(
+                f,
+                "{} does not exist, set EGUI_WGPU_UPDATE_GOLDEN to create it",
+                path.display()
+            ),
+            SnapshotError::SizeMismatch { expected, actual } => write!(
+                f,
+                "golden image is {:?} but the snapshot is {:?}",
+                expected, actual
+            ),
+            SnapshotError::PixelsDiffer { count, diff } => write!(
+                f,
+                "{} pixels differ from the golden image, see {}",
+                count,
+                diff.display()
+            ),
+            SnapshotError::Io(e) => write!(f, "{}", e),
+            SnapshotError::Encode(e) => write!(f, "{}", e),
+            SnapshotError::Decode(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for SnapshotError {}
+
+impl From<io::Error> for SnapshotError {
+    fn from(e: io::Error) -> Self {
+        SnapshotError::Io(e)
+    }
+}
+
+impl From<png::EncodingError> for SnapshotError {
+    fn from(e: png::EncodingError) -> Self {
+        SnapshotError::Encode(e)
+    }
+}
+
+impl From<png::DecodingError> for SnapshotError {
+    fn from(e: png::DecodingError) -> Self {
+        SnapshotError::Decode(e)
+    }
+}
+
+/// Requests any adapter that does not need a surface, this includes
+/// software Vulkan implementations such as lavapipe or swiftshader.
+pub fn headless_device() -> Option<(Device, Queue)> {
+    let instance = Instance::new(BackendBit::PRIMARY);
+    let adapter = block_on(instance.request_adapter(&RequestAdapterOptions {
+        power_preference: PowerPreference::default(),
+        compatible_surface: None,
+    }))?;
+    block_on(adapter.request_device(&DeviceDescriptor::default(), None)).ok()
+}
+
+/// Plays back the scripted frames and returns the last one. Time is
+/// driven by a [`ManualClock`] so animations render the same every run.
+pub fn snapshot<S: UiState>(desc: SnapshotDescriptor<S>) -> Result<RgbaImage, SnapshotError> {
+    let SnapshotDescriptor {
+        state,
+        size,
+        ppp,
+        mut frames,
+        backend,
+        ..
+    } = desc;
+    if frames.is_empty() {
+        frames.push(Vec::new());
+    }
+    match backend {
+        Backend::Gpu => gpu_snapshot(state, size, ppp, frames),
+        Backend::Software => Ok(software_snapshot(state, size, ppp, frames)),
+    }
+}
+
+fn gpu_snapshot<S: UiState>(
+    state: S,
+    size: (u32, u32),
+    ppp: f32,
+    frames: Vec<Vec<EventBridge>>,
+) -> Result<RgbaImage, SnapshotError> {
+    let (dev, queue) = headless_device().ok_or(SnapshotError::NoAdapter)?;
+
+    let mut renderer = EguiRenderer::new(
+        &dev,
+        &queue,
+        EguiRendererDescriptor {
+            state,
+            fmt: FMT,
+            screen_size: (size.0 as f32, size.1 as f32),
+            ppp,
+            load: LoadOp::Clear(Color::BLACK),
+            color_space: None,
+            hdr: None,
+            sample_count: 1,
+        },
+    );
+
+    // every frame is a 60th of a second apart, whatever the machine
+    let clock = ManualClock::default();
+    renderer.set_clock(clock.clone());
+
+    let last = frames.len() - 1;
+    for (i, events) in frames.into_iter().enumerate() {
+        clock.advance(1. / 60.);
+        events.into_iter().for_each(|e| renderer.consume_event(e));
+        if i != last {
+            renderer.prepare(&dev, &queue);
+        }
+    }
+
+    Ok(renderer.capture(&dev, &queue, size).0)
+}
+
+fn software_snapshot<S: UiState>(
+    state: S,
+    size: (u32, u32),
+    ppp: f32,
+    frames: Vec<Vec<EventBridge>>,
+) -> RgbaImage {
+    let mut ui = Frontend::new(state, (size.0 as f32, size.1 as f32), ppp);
+    let clock = ManualClock::default();
+    ui.clock = Box::new(clock.clone());
+
+    let mut jobs = Vec::new();
+    for events in frames {
+        clock.advance(1. / 60.);
+        events.into_iter().for_each(|e| ui.consume(e));
+        jobs = ui.run_frame().1;
+    }
+
+    let tex = ui.ctx.texture();
+    let mesh = Mesh::build(jobs, &[], (tex.width, tex.height));
+    rasterize_mesh(&mesh, tex, size, ppp)
+}
+
+/// Renders `desc` and compares it against the PNG at `golden`. On failure
+/// the snapshot and a diff image are written next to the golden image.
+pub fn assert_snapshot<S: UiState>(
+    desc: SnapshotDescriptor<S>,
+    golden: impl AsRef<Path>,
+) -> Result<(), SnapshotError> {
+    let tolerance = desc.tolerance;
+    let actual = snapshot(desc)?;
+    compare_to_golden(&actual, golden.as_ref(), tolerance)
+}
+
+pub fn compare_to_golden(
+    actual: &RgbaImage,
+    golden: &Path,
+    tolerance: u8,
+) -> Result<(), SnapshotError> {
+    if std::env::var_os("EGUI_WGPU_UPDATE_GOLDEN").is_some() {
+        return Ok(actual.write_png(File::create(golden)?)?);
+    }
+    if !golden.exists() {
+        return Err(SnapshotError::MissingGolden(golden.to_owned()));
+    }
+
+    let expected = RgbaImage::read_png(File::open(golden)?)?;
+    if (expected.width, expected.height) != (actual.width, actual.height) {
+        return Err(SnapshotError::SizeMismatch {
+            expected: (expected.width, expected.height),
+            actual: (actual.width, actual.height),
+        });
+    }
+
+    let (count, diff) = diff_images(&expected, actual, tolerance);
+    if count == 0 {
+        return Ok(());
+    }
+
+    let diff_path = golden.with_extension("diff.png");
+    diff.write_png(File::create(&diff_path)?)?;
+    actual.write_png(File::create(golden.with_extension("actual.png"))?)?;
+    Err(SnapshotError::PixelsDiffer {
+        count,
+        diff: diff_path,
+    })
+}
+
+/// Counts the pixels that differ by more than `tolerance` in any channel.
+/// The diff image marks them red over a faded copy of `expected`.
+fn diff_images(expected: &RgbaImage, actual: &RgbaImage, tolerance: u8) -> (usize, RgbaImage) {
+    let mut count = 0;
+    let pixels = expected
+        .pixels
+        .chunks(4)
+        .zip(actual.pixels.chunks(4))
+        .flat_map(|(e, a)| {
+            let differs = e
+                .iter()
+                .zip(a)
+                .any(|(e, a)| (*e as i16 - *a as i16).abs() > tolerance as i16);
+            if differs {
+                count += 1;
+                vec![255, 0, 0, 255]
+            } else {
+                vec![e[0] / 4, e[1] / 4, e[2] / 4, 255]
+            }
+        })
+        .collect();
+
+    (
+        count,
+        RgbaImage {
+            width: expected.width,
+            height: expected.height,
+            pixels,
+        },
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn image(pixels: Vec<u8>) -> RgbaImage {
+        RgbaImage {
+            width: pixels.len() as u32 / 4,
+            height: 1,
+            pixels,
+        }
+    }
+
+    #[test]
+    fn differences_within_tolerance_pass() {
+        let a = image(vec![10, 20, 30, 255, 0, 0, 0, 0]);
+        let b = image(vec![12, 18, 30, 255, 0, 0, 0, 2]);
+        assert_eq!(diff_images(&a, &b, 2).0, 0);
+    }
+
+    #[test]
+    fn differing_pixels_are_counted_and_marked() {
+        let a = image(vec![10, 20, 30, 255, 0, 0, 0, 0]);
+        let b = image(vec![10, 20, 30, 255, 0, 0, 90, 0]);
+        let (count, diff) = diff_images(&a, &b, 2);
+        assert_eq!(count, 1);
+        assert_eq!(&diff.pixels[4..], &[255, 0, 0, 255]);
+    }
+
+    #[test]
+    fn missing_golden_is_an_error() {
+        if std::env::var_os("EGUI_WGPU_UPDATE_GOLDEN").is_some() {
+            return;
+        }
+        let golden = std::env::temp_dir().join("egui-wgpu-no-such-golden.png");
+        let result = compare_to_golden(&image(vec![0; 4]), &golden, 0);
+        assert!(matches!(result, Err(SnapshotError::MissingGolden(_))));
+        assert!(!golden.exists());
+    }
+
+    struct Label;
+
+    impl UiState for Label {
+        type Action = ();
+
+        fn draw(&mut self, ui: &mut egui::Ui) -> Option<()> {
+            ui.label("Hello");
+            None
+        }
+    }
+
+    #[test]
+    fn software_snapshot_draws_the_ui() {
+        let image = snapshot(SnapshotDescriptor {
+            state: Label,
+            size: (64, 32),
+            ppp: 1.,
+            frames: vec![vec![], vec![]],
+            tolerance: 0,
+            backend: Backend::Software,
+        })
+        .unwrap();
+        assert_eq!((image.width, image.height), (64, 32));
+        assert!(image.pixels.chunks(4).any(|px| px[0] > 0));
+    }
+
+    #[test]
+    fn no_frames_still_draws_one() {
+        let desc = |frames| SnapshotDescriptor {
+            state: Label,
+            size: (64, 32),
+            ppp: 1.,
+            frames,
+            tolerance: 0,
+            backend: Backend::Software,
+        };
+        assert_eq!(
+            snapshot(desc(vec![])).unwrap(),
+            snapshot(desc(vec![vec![]])).unwrap()
+        );
+    }
+}
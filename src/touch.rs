@@ -0,0 +1,164 @@
+use egui::{Pos2, Vec2};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TouchPhase {
+    Start,
+    Move,
+    End,
+    Cancel,
+}
+
+/// Pointer behaviour synthesized from touches.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Synthesized {
+    Move(Pos2),
+    Down,
+    Up,
+    /// The pointer is no longer over the window.
+    Leave,
+    Scroll(Vec2),
+    /// Multiplicative change of the distance between two fingers.
+    Zoom(f32),
+}
+
+/// Tracks the fingers currently down. The first finger of a gesture
+/// drives the pointer, a second one turns it into a pinch/pan gesture.
+#[derive(Debug, Default)]
+pub struct Touches {
+    active: Vec<(u64, Pos2)>,
+    /// The last finger was lifted, the pointer leaves once egui has seen
+    /// the release.
+    leave_pending: bool,
+}
+
+impl Touches {
+    pub fn update(&mut self, id: u64, phase: TouchPhase, pos: Pos2) -> Vec<Synthesized> {
+        match phase {
+            TouchPhase::Start => {
+                self.leave_pending = false;
+                self.active.push((id, pos));
+                match self.active.len() {
+                    1 => vec![Synthesized::Move(pos), Synthesized::Down],
+                    // a second finger starts a gesture, let go of whatever
+                    // the first one was dragging
+                    2 => vec![Synthesized::Up],
+                    _ => vec![],
+                }
+            }
+            TouchPhase::Move => {
+                let idx = match self.active.iter().position(|(i, _)| *i == id) {
+                    Some(idx) => idx,
+                    None => return vec![],
+                };
+                let before = self.pair();
+                self.active[idx].1 = pos;
+                if self.active.len() == 1 {
+                    return vec![Synthesized::Move(pos)];
+                }
+
+                match (before, self.pair()) {
+                    (Some((c0, d0)), Some((c1, d1))) if idx < 2 => {
+                        let mut out = vec![Synthesized::Scroll(c1 - c0)];
+                        if d0 > 0. {
+                            out.push(Synthesized::Zoom(d1 / d0));
+                        }
+                        out
+                    }
+                    _ => vec![],
+                }
+            }
+            TouchPhase::End | TouchPhase::Cancel => {
+                let idx = match self.active.iter().position(|(i, _)| *i == id) {
+                    Some(idx) => idx,
+                    None => return vec![],
+                };
+                self.active.remove(idx);
+                if !self.active.is_empty() {
+                    return vec![];
+                }
+                if phase == TouchPhase::End {
+                    // egui 0.1 drops the click if the pointer leaves in
+                    // the same frame
+                    self.leave_pending = true;
+                    vec![Synthesized::Up]
+                } else {
+                    // move away before releasing so nothing gets clicked
+                    vec![Synthesized::Leave, Synthesized::Up]
+                }
+            }
+        }
+    }
+
+    /// Called after each frame, returns what had to wait for it.
+    pub fn end_frame(&mut self) -> Vec<Synthesized> {
+        if std::mem::take(&mut self.leave_pending) {
+            vec![Synthesized::Leave]
+        } else {
+            vec![]
+        }
+    }
+
+    /// Centroid and distance of the first two fingers.
+    fn pair(&self) -> Option<(Pos2, f32)> {
+        match self.active.as_slice() {
+            [(_, a), (_, b), ..] => Some((*a + (*b - *a) * 0.5, (*b - *a).length())),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        Synthesized::{Down, Leave, Scroll, Up, Zoom},
+        TouchPhase::{Cancel, End, Start},
+        *,
+    };
+    use egui::{pos2, vec2};
+
+    #[test]
+    fn single_finger_drives_the_pointer() {
+        let mut t = Touches::default();
+        assert_eq!(
+            t.update(1, Start, pos2(1., 2.)),
+            vec![Synthesized::Move(pos2(1., 2.)), Down]
+        );
+        assert_eq!(
+            t.update(1, TouchPhase::Move, pos2(3., 4.)),
+            vec![Synthesized::Move(pos2(3., 4.))]
+        );
+        assert_eq!(t.update(1, End, pos2(3., 4.)), vec![Up]);
+        // nothing is hovered once the click went through
+        assert_eq!(t.end_frame(), vec![Leave]);
+        assert_eq!(t.end_frame(), vec![]);
+    }
+
+    #[test]
+    fn touching_again_keeps_the_pointer() {
+        let mut t = Touches::default();
+        t.update(1, Start, pos2(1., 2.));
+        t.update(1, End, pos2(1., 2.));
+        t.update(2, Start, pos2(5., 6.));
+        assert_eq!(t.end_frame(), vec![]);
+    }
+
+    #[test]
+    fn cancel_does_not_click() {
+        let mut t = Touches::default();
+        t.update(1, Start, pos2(1., 2.));
+        assert_eq!(t.update(1, Cancel, pos2(1., 2.)), vec![Leave, Up]);
+    }
+
+    #[test]
+    fn two_fingers_pan_and_pinch() {
+        let mut t = Touches::default();
+        t.update(1, Start, pos2(0., 0.));
+        assert_eq!(t.update(2, Start, pos2(10., 0.)), vec![Up]);
+        assert_eq!(
+            t.update(2, TouchPhase::Move, pos2(20., 0.)),
+            vec![Scroll(vec2(5., 0.)), Zoom(2.)]
+        );
+        assert_eq!(t.update(1, End, pos2(0., 0.)), vec![]);
+        assert_eq!(t.update(2, End, pos2(20., 0.)), vec![Up]);
+    }
+}
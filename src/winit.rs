@@ -0,0 +1,293 @@
+//! Translation from winit events to [`EventBridge`].
+
+use crate::{EguiRenderer, EventBridge, Key, Modifiers, TouchPhase, UiState};
+use ::winit::event::{
+    self, ElementState, Event, KeyboardInput, ModifiersState, MouseButton, MouseScrollDelta, Touch,
+    VirtualKeyCode, WindowEvent,
+};
+
+/// Translates any winit event, everything that is not a window event
+/// is ignored.
+pub fn translate<T>(event: &Event<T>) -> Vec<EventBridge> {
+    match event {
+        Event::WindowEvent { event, .. } => translate_window_event(event),
+        _ => vec![],
+    }
+}
+
+pub fn translate_window_event(event: &WindowEvent) -> Vec<EventBridge> {
+    let event = match event {
+        WindowEvent::Resized(size) => EventBridge::Resize {
+            w: size.width as f32,
+            h: size.height as f32,
+        },
+        WindowEvent::ScaleFactorChanged {
+            scale_factor,
+            new_inner_size,
+        } => {
+            return vec![
+                EventBridge::PppChanged(*scale_factor as f32),
+                EventBridge::Resize {
+                    w: new_inner_size.width as f32,
+                    h: new_inner_size.height as f32,
+                },
+            ]
+        }
+        WindowEvent::CursorMoved { position: p, .. } => EventBridge::MouseMove {
+            x: p.x as f32,
+            y: p.y as f32,
+        },
+        WindowEvent::MouseWheel { delta, .. } => match delta {
+            MouseScrollDelta::LineDelta(x, y) => EventBridge::ScrollLines { x: *x, y: *y },
+            // winit already reports these in logical pixels
+            MouseScrollDelta::PixelDelta(p) => EventBridge::Scroll {
+                x: p.x as f32,
+                y: p.y as f32,
+            },
+        },
+        WindowEvent::MouseInput { state, button, .. } => {
+            let button = mouse_button(*button);
+            match state {
+                ElementState::Pressed => EventBridge::MouseDown { button, pos: None },
+                ElementState::Released => EventBridge::MouseUp { button, pos: None },
+            }
+        }
+        WindowEvent::KeyboardInput {
+            input:
+                KeyboardInput {
+                    virtual_keycode: Some(code),
+                    state,
+                    ..
+                },
+            ..
+        } => EventBridge::Key {
+            key: key(*code),
+            pressed: *state == ElementState::Pressed,
+        },
+        WindowEvent::ModifiersChanged(m) => EventBridge::Modifiers(modifiers(*m)),
+        WindowEvent::ReceivedCharacter(c) => EventBridge::Char(*c),
+        WindowEvent::CursorLeft { .. } => EventBridge::PointerLeft,
+        WindowEvent::Focused(focused) => EventBridge::Focus(*focused),
+        WindowEvent::Touch(Touch {
+            id,
+            phase,
+            location,
+            ..
+        }) => EventBridge::Touch {
+            id: *id,
+            phase: touch_phase(*phase),
+            x: location.x as f32,
+            y: location.y as f32,
+        },
+        _ => EventBridge::Ignore,
+    };
+    vec![event]
+}
+
+fn mouse_button(button: MouseButton) -> crate::MouseButton {
+    match button {
+        MouseButton::Left => crate::MouseButton::Primary,
+        MouseButton::Right => crate::MouseButton::Secondary,
+        MouseButton::Middle => crate::MouseButton::Middle,
+        MouseButton::Other(n) => crate::MouseButton::Extra(n),
+    }
+}
+
+fn touch_phase(phase: event::TouchPhase) -> TouchPhase {
+    match phase {
+        event::TouchPhase::Started => TouchPhase::Start,
+        event::TouchPhase::Moved => TouchPhase::Move,
+        event::TouchPhase::Ended => TouchPhase::End,
+        event::TouchPhase::Cancelled => TouchPhase::Cancel,
+    }
+}
+
+fn modifiers(m: ModifiersState) -> Modifiers {
+    Modifiers {
+        alt: m.alt(),
+        ctrl: m.ctrl(),
+        shift: m.shift(),
+        logo: m.logo(),
+    }
+}
+
+fn key(code: VirtualKeyCode) -> Key {
+    use VirtualKeyCode::*;
+    match code {
+        LAlt | RAlt => Key::Alt,
+        Back => Key::Backspace,
+        LControl | RControl => Key::Control,
+        Delete => Key::Delete,
+        Down => Key::Down,
+        End => Key::End,
+        Return | NumpadEnter => Key::Enter,
+        Escape => Key::Escape,
+        Home => Key::Home,
+        Insert => Key::Insert,
+        Left => Key::Left,
+        LWin | RWin => Key::Logo,
+        PageDown => Key::PageDown,
+        PageUp => Key::PageUp,
+        Right => Key::Right,
+        LShift | RShift => Key::Shift,
+        Tab => Key::Tab,
+        Up => Key::Up,
+        C => Key::C,
+        V => Key::V,
+        X => Key::X,
+        _ => Key::Other,
+    }
+}
+
+impl<S> EguiRenderer<S>
+where
+    S: UiState,
+{
+    /// Feeds a winit event to the UI.
+    pub fn consume_winit_event<T>(&mut self, event: &Event<T>) {
+        translate(event)
+            .into_iter()
+            .for_each(|e| self.consume_event(e));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ::winit::{
+        dpi::{LogicalPosition, PhysicalPosition, PhysicalSize},
+        event::DeviceId,
+    };
+
+    fn dev() -> DeviceId {
+        unsafe { DeviceId::dummy() }
+    }
+
+    #[test]
+    fn line_deltas_stay_in_lines() {
+        #[allow(deprecated)]
+        let event = WindowEvent::MouseWheel {
+            device_id: dev(),
+            delta: MouseScrollDelta::LineDelta(1., -2.),
+            phase: event::TouchPhase::Moved,
+            modifiers: Default::default(),
+        };
+        assert_eq!(
+            translate_window_event(&event),
+            vec![EventBridge::ScrollLines { x: 1., y: -2. }]
+        );
+    }
+
+    #[test]
+    fn pixel_deltas_pass_through() {
+        #[allow(deprecated)]
+        let event = WindowEvent::MouseWheel {
+            device_id: dev(),
+            delta: MouseScrollDelta::PixelDelta(LogicalPosition::new(3., 4.)),
+            phase: event::TouchPhase::Moved,
+            modifiers: Default::default(),
+        };
+        assert_eq!(
+            translate_window_event(&event),
+            vec![EventBridge::Scroll { x: 3., y: 4. }]
+        );
+    }
+
+    #[test]
+    fn scale_factor_change_also_resizes() {
+        let mut size = PhysicalSize::new(200, 100);
+        let event = WindowEvent::ScaleFactorChanged {
+            scale_factor: 2.,
+            new_inner_size: &mut size,
+        };
+        assert_eq!(
+            translate_window_event(&event),
+            vec![
+                EventBridge::PppChanged(2.),
+                EventBridge::Resize { w: 200., h: 100. }
+            ]
+        );
+    }
+
+    #[test]
+    fn keys_and_modifiers_are_translated() {
+        #[allow(deprecated)]
+        let event = WindowEvent::KeyboardInput {
+            device_id: dev(),
+            input: KeyboardInput {
+                scancode: 0,
+                state: ElementState::Pressed,
+                virtual_keycode: Some(VirtualKeyCode::Back),
+                modifiers: Default::default(),
+            },
+            is_synthetic: false,
+        };
+        assert_eq!(
+            translate_window_event(&event),
+            vec![EventBridge::Key {
+                key: Key::Backspace,
+                pressed: true
+            }]
+        );
+
+        let event = WindowEvent::ModifiersChanged(ModifiersState::CTRL);
+        assert_eq!(
+            translate_window_event(&event),
+            vec![EventBridge::Modifiers(Modifiers {
+                ctrl: true,
+                ..Default::default()
+            })]
+        );
+    }
+
+    #[test]
+    fn buttons_keep_their_identity() {
+        #[allow(deprecated)]
+        let event = WindowEvent::MouseInput {
+            device_id: dev(),
+            state: ElementState::Pressed,
+            button: MouseButton::Right,
+            modifiers: Default::default(),
+        };
+        assert_eq!(
+            translate_window_event(&event),
+            vec![EventBridge::MouseDown {
+                button: crate::MouseButton::Secondary,
+                pos: None
+            }]
+        );
+    }
+
+    #[test]
+    fn touches_are_translated() {
+        let event = WindowEvent::Touch(Touch {
+            device_id: dev(),
+            phase: event::TouchPhase::Started,
+            location: PhysicalPosition::new(5., 6.),
+            force: None,
+            id: 7,
+        });
+        assert_eq!(
+            translate_window_event(&event),
+            vec![EventBridge::Touch {
+                id: 7,
+                phase: TouchPhase::Start,
+                x: 5.,
+                y: 6.
+            }]
+        );
+    }
+
+    #[test]
+    fn leaving_and_focus_are_translated() {
+        let event = WindowEvent::CursorLeft { device_id: dev() };
+        assert_eq!(
+            translate_window_event(&event),
+            vec![EventBridge::PointerLeft]
+        );
+        assert_eq!(
+            translate_window_event(&WindowEvent::Focused(false)),
+            vec![EventBridge::Focus(false)]
+        );
+    }
+}